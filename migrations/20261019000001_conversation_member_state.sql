@@ -0,0 +1,13 @@
+-- 会话成员的个人状态：置顶 / 归档 / 隐藏 / 免打扰 / 清空历史
+-- 这些状态只影响当前成员自己的会话列表和历史消息，不影响其他成员
+ALTER TABLE conversation_member
+  ADD COLUMN is_pinned   BOOLEAN  NOT NULL DEFAULT FALSE,
+  ADD COLUMN pinned_at   DATETIME NULL,
+  ADD COLUMN is_archived BOOLEAN  NOT NULL DEFAULT FALSE,
+  ADD COLUMN is_hidden   BOOLEAN  NOT NULL DEFAULT FALSE,
+  ADD COLUMN is_muted    BOOLEAN  NOT NULL DEFAULT FALSE,
+  ADD COLUMN cleared_at  DATETIME NULL;
+
+-- 会话列表按用户查询，历史消息按会话 + 时间查询
+CREATE INDEX idx_conversation_member_user ON conversation_member (user_id, is_hidden, is_archived);
+CREATE INDEX idx_messages_conversation_created ON messages (conversation_id, created_at);
@@ -0,0 +1,9 @@
+-- 用户拉黑关系：blocker_id 拉黑了 blocked_id
+CREATE TABLE user_blocks (
+  id         BIGINT   NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  blocker_id BIGINT   NOT NULL,
+  blocked_id BIGINT   NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_user_blocks (blocker_id, blocked_id),
+  KEY idx_user_blocks_blocked (blocked_id)
+);
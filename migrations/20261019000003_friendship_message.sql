@@ -0,0 +1,3 @@
+-- 好友请求附言
+ALTER TABLE friendships
+  ADD COLUMN message VARCHAR(255) NULL;
@@ -0,0 +1,24 @@
+-- 好友标签 (分组)，只对创建者可见
+CREATE TABLE friend_labels (
+  id         BIGINT      NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT      NOT NULL,
+  name       VARCHAR(64) NOT NULL,
+  created_at DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_friend_labels_user_name (user_id, name)
+);
+
+CREATE TABLE friend_label_members (
+  label_id  BIGINT NOT NULL,
+  friend_id BIGINT NOT NULL,
+  PRIMARY KEY (label_id, friend_id),
+  KEY idx_friend_label_members_friend (friend_id)
+);
+
+-- 笔记共享：note_id 共享给 user_id (只读)
+CREATE TABLE note_shares (
+  note_id    BIGINT   NOT NULL,
+  user_id    BIGINT   NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (note_id, user_id),
+  KEY idx_note_shares_user (user_id)
+);
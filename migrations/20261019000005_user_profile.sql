@@ -0,0 +1,6 @@
+-- 用户资料：昵称 / 头像 / 个人简介 / 状态
+ALTER TABLE users
+  ADD COLUMN display_name VARCHAR(64)  NULL,
+  ADD COLUMN avatar_url   VARCHAR(255) NULL,
+  ADD COLUMN bio          VARCHAR(500) NULL,
+  ADD COLUMN status_text  VARCHAR(100) NULL;
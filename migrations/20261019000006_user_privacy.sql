@@ -0,0 +1,14 @@
+-- 隐私设置：everyone / friends / nobody
+--   username_search  谁能通过用户名/昵称搜到我
+--   contact_search   谁能通过完整的邮箱/手机号搜到我
+--   email_visibility 谁能看到我的邮箱和手机号
+ALTER TABLE users
+  ADD COLUMN phone            VARCHAR(32) NULL,
+  ADD COLUMN username_search  VARCHAR(16) NOT NULL DEFAULT 'everyone',
+  ADD COLUMN contact_search   VARCHAR(16) NOT NULL DEFAULT 'everyone',
+  ADD COLUMN email_visibility VARCHAR(16) NOT NULL DEFAULT 'friends';
+
+-- 前缀搜索 (LIKE 'q%') 和精确查找都走索引
+CREATE INDEX idx_users_display_name ON users (display_name);
+CREATE INDEX idx_users_email ON users (email);
+CREATE INDEX idx_users_phone ON users (phone);
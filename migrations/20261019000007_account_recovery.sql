@@ -0,0 +1,17 @@
+-- token_version：改密码 / 重置密码 / 强制下线时 +1，旧 JWT 全部失效
+ALTER TABLE users
+  ADD COLUMN token_version     INT      NOT NULL DEFAULT 0,
+  ADD COLUMN email_verified_at DATETIME NULL;
+
+-- 一次性、有过期时间的令牌 (重置密码、邮箱验证)，只存 sha256
+CREATE TABLE user_tokens (
+  id         BIGINT      NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT      NOT NULL,
+  purpose    VARCHAR(32) NOT NULL,
+  token_hash CHAR(64)    NOT NULL,
+  expires_at DATETIME    NOT NULL,
+  used_at    DATETIME    NULL,
+  created_at DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_user_tokens_hash (token_hash),
+  KEY idx_user_tokens_user (user_id, purpose)
+);
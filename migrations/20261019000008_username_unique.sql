@@ -0,0 +1,12 @@
+-- 用户名大小写不敏感唯一，由数据库保证 (register 不再先 COUNT 再 INSERT)
+-- 先处理已有的重复 (只差大小写)：保留最早注册的，其余改名为 `原名#id`
+-- 注册用户名不允许 `#`，改出来的名字不会和别人冲突
+UPDATE users u
+JOIN users k
+  ON LOWER(k.username) = LOWER(u.username)
+  AND k.id < u.id
+SET u.username = CONCAT(u.username, '#', u.id);
+
+ALTER TABLE users
+  ADD COLUMN username_lower VARCHAR(64) AS (LOWER(username)) STORED,
+  ADD UNIQUE KEY uk_users_username_lower (username_lower);
@@ -0,0 +1,18 @@
+-- TOTP 两步验证
+-- totp_secret 绑定确认前就写入，totp_enabled_at 不为空才算开启
+-- totp_last_step 记录最后一次使用的时间窗口，防止验证码重放
+ALTER TABLE users
+  ADD COLUMN totp_secret         VARCHAR(64) NULL,
+  ADD COLUMN totp_enabled_at     DATETIME    NULL,
+  ADD COLUMN totp_last_step      BIGINT      NULL,
+  ADD COLUMN two_factor_required BOOLEAN     NOT NULL DEFAULT FALSE;
+
+-- 一次性恢复码，只存 sha256
+CREATE TABLE user_recovery_codes (
+  id         BIGINT   NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT   NOT NULL,
+  code_hash  CHAR(64) NOT NULL,
+  used_at    DATETIME NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_recovery_codes_user_hash (user_id, code_hash)
+);
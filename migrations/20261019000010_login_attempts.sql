@@ -0,0 +1,13 @@
+-- 登录尝试审计，同时用于按账号 / 按 IP 的失败限流
+-- username 存小写 (不存在的用户名也记录)，reason: ok / unknown_user / bad_password / bad_2fa / locked
+CREATE TABLE login_attempts (
+  id         BIGINT      NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  username   VARCHAR(64) NOT NULL,
+  user_id    BIGINT      NULL,
+  ip         VARCHAR(45) NOT NULL,
+  success    BOOLEAN     NOT NULL,
+  reason     VARCHAR(32) NOT NULL,
+  created_at DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  KEY idx_login_attempts_username (username, created_at),
+  KEY idx_login_attempts_ip (ip, created_at)
+);
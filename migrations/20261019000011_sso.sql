@@ -0,0 +1,22 @@
+-- 外部身份 (OIDC issuer + sub) 与本地用户的关联，一个用户在每个 issuer 下只能关联一个身份
+CREATE TABLE user_identities (
+  id         BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT       NOT NULL,
+  issuer     VARCHAR(255) NOT NULL,
+  subject    VARCHAR(255) NOT NULL,
+  email      VARCHAR(255) NULL,
+  created_at DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_user_identities_subject (issuer, subject),
+  UNIQUE KEY uk_user_identities_user (user_id, issuer)
+);
+
+-- 授权请求的 state，回调时一次性取出 PKCE code_verifier 和 nonce
+-- user_id 不为空表示已登录用户发起的账号关联
+CREATE TABLE oidc_states (
+  state_hash    CHAR(64)     NOT NULL PRIMARY KEY,
+  code_verifier VARCHAR(128) NOT NULL,
+  nonce         VARCHAR(128) NOT NULL,
+  user_id       BIGINT       NULL,
+  expires_at    DATETIME     NOT NULL,
+  created_at    DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
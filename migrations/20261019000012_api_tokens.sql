@@ -0,0 +1,15 @@
+-- 个人访问令牌，只存 sha256；scopes 为 JSON 数组，例如 ["notes:read","notes:write"]
+CREATE TABLE api_tokens (
+  id           BIGINT      NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id      BIGINT      NOT NULL,
+  name         VARCHAR(64) NOT NULL,
+  token_prefix VARCHAR(16) NOT NULL,
+  token_hash   CHAR(64)    NOT NULL,
+  scopes       JSON        NOT NULL,
+  last_used_at DATETIME    NULL,
+  expires_at   DATETIME    NULL,
+  revoked_at   DATETIME    NULL,
+  created_at   DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_api_tokens_hash (token_hash),
+  KEY idx_api_tokens_user (user_id)
+);
@@ -0,0 +1,17 @@
+-- 系统角色与账号禁用
+-- 第一个管理员需要手动指定：UPDATE users SET role = 'admin' WHERE username = '...';
+ALTER TABLE users
+  ADD COLUMN role            VARCHAR(16)  NOT NULL DEFAULT 'user',
+  ADD COLUMN disabled_at     DATETIME     NULL,
+  ADD COLUMN disabled_reason VARCHAR(255) NULL;
+
+-- 管理操作审计
+CREATE TABLE admin_actions (
+  id             BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  admin_id       BIGINT       NOT NULL,
+  action         VARCHAR(32)  NOT NULL,
+  target_user_id BIGINT       NULL,
+  detail         VARCHAR(255) NULL,
+  created_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  KEY idx_admin_actions_target (target_user_id, created_at)
+);
@@ -0,0 +1,6 @@
+-- 账号注销：deletion_scheduled_at 到期后由后台任务清理数据
+-- 清理后 users 行保留为匿名占位 (username = deleted-{id})，聊天记录的 sender_id 仍然有效
+ALTER TABLE users
+  ADD COLUMN deletion_scheduled_at DATETIME NULL,
+  ADD COLUMN deleted_at            DATETIME NULL,
+  ADD KEY idx_users_deletion (deletion_scheduled_at);
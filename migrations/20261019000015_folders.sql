@@ -0,0 +1,25 @@
+-- 笔记文件夹 (笔记本)，parent_id 为空表示顶层，可以任意嵌套
+CREATE TABLE folders (
+  id         BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT       NOT NULL,
+  parent_id  BIGINT       NULL,
+  name       VARCHAR(128) NOT NULL,
+  created_at DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+  KEY idx_folders_user_parent (user_id, parent_id),
+  KEY idx_folders_parent (parent_id)
+);
+
+-- 文件夹共享：folder_id 及其所有子文件夹里的笔记对 user_id 只读可见
+CREATE TABLE folder_shares (
+  folder_id  BIGINT   NOT NULL,
+  user_id    BIGINT   NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (folder_id, user_id),
+  KEY idx_folder_shares_user (user_id)
+);
+
+-- 笔记所在文件夹，为空表示未归档 (根目录)
+ALTER TABLE notes
+  ADD COLUMN folder_id BIGINT NULL,
+  ADD KEY idx_notes_folder (folder_id);
@@ -0,0 +1,15 @@
+-- 笔记标签，每个用户一套；名字大小写不敏感唯一 (默认 collation)
+CREATE TABLE tags (
+  id         BIGINT      NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id    BIGINT      NOT NULL,
+  name       VARCHAR(64) NOT NULL,
+  created_at DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_tags_user_name (user_id, name)
+);
+
+CREATE TABLE note_tags (
+  note_id BIGINT NOT NULL,
+  tag_id  BIGINT NOT NULL,
+  PRIMARY KEY (note_id, tag_id),
+  KEY idx_note_tags_tag (tag_id)
+);
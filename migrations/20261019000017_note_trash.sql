@@ -0,0 +1,4 @@
+-- 笔记回收站：删除只设置 deleted_at，保留期过后由后台任务彻底删除
+ALTER TABLE notes
+  ADD COLUMN deleted_at DATETIME NULL,
+  ADD KEY idx_notes_user_deleted (user_id, deleted_at);
@@ -0,0 +1,10 @@
+-- 笔记内容格式 plain / markdown，excerpt 是列表页用的纯文本摘要 (保存时生成)
+-- 已有笔记是按纯文本写的，先以 plain 加列，再把新笔记的默认值改成 markdown
+ALTER TABLE notes
+  ADD COLUMN content_format VARCHAR(16)  NOT NULL DEFAULT 'plain',
+  ADD COLUMN excerpt        VARCHAR(255) NULL;
+
+ALTER TABLE notes ALTER COLUMN content_format SET DEFAULT 'markdown';
+
+-- 旧数据先用正文开头近似，下次保存时重新生成
+UPDATE notes SET excerpt = LEFT(content, 200) WHERE content IS NOT NULL;
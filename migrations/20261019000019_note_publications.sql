@@ -0,0 +1,14 @@
+-- 笔记公开链接，每篇笔记最多一个；token 就是 URL，明文保存方便作者再次复制
+-- 访问密码只存 bcrypt
+CREATE TABLE note_publications (
+  id             BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  note_id        BIGINT       NOT NULL,
+  token          VARCHAR(64)  NOT NULL,
+  password_hash  VARCHAR(255) NULL,
+  expires_at     DATETIME     NULL,
+  view_count     BIGINT       NOT NULL DEFAULT 0,
+  last_viewed_at DATETIME     NULL,
+  created_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_note_publications_note (note_id),
+  UNIQUE KEY uk_note_publications_token (token)
+);
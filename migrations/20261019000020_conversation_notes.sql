@@ -0,0 +1,13 @@
+-- 聊天里的笔记卡片：msg_type = 'note' 的消息通过 note_id 引用笔记
+ALTER TABLE messages ADD COLUMN note_id BIGINT NULL;
+
+-- 分享到会话的笔记，分享期间会话成员都可以读取
+CREATE TABLE conversation_notes (
+  id              BIGINT   NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  conversation_id BIGINT   NOT NULL,
+  note_id         BIGINT   NOT NULL,
+  shared_by       BIGINT   NOT NULL,
+  created_at      DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_conversation_notes (conversation_id, note_id),
+  KEY idx_conversation_notes_note (note_id)
+);
@@ -0,0 +1,17 @@
+-- 笔记附件，文件本身在 Storage 的 attachments/{user_id}/{token} 下
+-- token 同时用于公开地址 /files/attachments/{token}，Markdown 里直接引用
+-- 笔记被彻底删除后附件由后台任务清理
+CREATE TABLE note_attachments (
+  id           BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  note_id      BIGINT       NOT NULL,
+  user_id      BIGINT       NOT NULL,
+  token        VARCHAR(64)  NOT NULL,
+  filename     VARCHAR(255) NOT NULL,
+  content_type VARCHAR(128) NOT NULL,
+  size         BIGINT       NOT NULL,
+  storage_key  VARCHAR(255) NOT NULL,
+  created_at   DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE KEY uk_note_attachments_token (token),
+  KEY idx_note_attachments_note (note_id),
+  KEY idx_note_attachments_user (user_id)
+);
@@ -0,0 +1,11 @@
+-- 正文里的 [[标题]] / [[id]] 链接，保存时重新解析
+-- target 是正文里写的内容，target_id 为空表示断链 (目标不存在)
+CREATE TABLE note_links (
+  id        BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  source_id BIGINT       NOT NULL,
+  target    VARCHAR(200) NOT NULL,
+  target_id BIGINT       NULL,
+  KEY idx_note_links_source (source_id),
+  KEY idx_note_links_target_id (target_id),
+  KEY idx_note_links_target (target)
+);
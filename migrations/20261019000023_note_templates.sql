@@ -0,0 +1,39 @@
+-- 笔记模板，user_id 为空的是全局模板 (内置的和管理员维护的)，所有用户可见
+-- 标题和正文里的 {{date}}、{{username}} 等占位符在创建笔记时替换
+CREATE TABLE note_templates (
+  id             BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id        BIGINT       NULL,
+  name           VARCHAR(100) NOT NULL,
+  title_pattern  VARCHAR(200) NOT NULL,
+  content        MEDIUMTEXT   NOT NULL,
+  content_format VARCHAR(16)  NOT NULL DEFAULT 'markdown',
+  created_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+  KEY idx_note_templates_user (user_id)
+);
+
+INSERT INTO note_templates (user_id, name, title_pattern, content) VALUES
+(NULL, '会议纪要', '会议纪要 {{date}}', '# {{title}}
+
+- 时间：{{date}} {{time}}
+- 记录人：{{name}}
+- 参会人：
+
+## 议题
+
+## 讨论
+
+## 结论
+
+## 待办
+
+- [ ] 
+'),
+(NULL, '每日日志', '{{date}} {{weekday}} 日志', '# {{title}}
+
+## 昨天完成
+
+## 今天计划
+
+## 遇到的问题
+');
@@ -0,0 +1,15 @@
+-- 两人之间只允许一条好友记录 (不分方向)，A→B 和 B→A 并发发送请求时只有一条能插入成功
+-- 先清理已有的重复记录：优先保留已接受的，其次保留最早的
+DELETE f FROM friendships f
+JOIN friendships k
+  ON LEAST(k.requester_id, k.receiver_id) = LEAST(f.requester_id, f.receiver_id)
+  AND GREATEST(k.requester_id, k.receiver_id) = GREATEST(f.requester_id, f.receiver_id)
+  AND (
+    (k.status = 'accepted' AND f.status <> 'accepted')
+    OR ((k.status = 'accepted') = (f.status = 'accepted') AND k.id < f.id)
+  );
+
+ALTER TABLE friendships
+  ADD COLUMN user_low  BIGINT AS (LEAST(requester_id, receiver_id)) STORED,
+  ADD COLUMN user_high BIGINT AS (GREATEST(requester_id, receiver_id)) STORED,
+  ADD UNIQUE KEY uk_friendships_pair (user_low, user_high);
@@ -0,0 +1,13 @@
+-- 同一个邮箱最多只能被一个账号验证，找回密码和 SSO 自动关联按已验证邮箱唯一定位账号
+-- 先清理已有的重复：保留最早验证的 (同时验证的保留 id 小的)，其余改回未验证
+UPDATE users u
+JOIN users k
+  ON k.email = u.email
+  AND k.email_verified_at IS NOT NULL
+  AND (k.email_verified_at < u.email_verified_at OR (k.email_verified_at = u.email_verified_at AND k.id < u.id))
+SET u.email_verified_at = NULL
+WHERE u.email_verified_at IS NOT NULL;
+
+ALTER TABLE users
+  ADD COLUMN verified_email VARCHAR(255) AS (IF(email_verified_at IS NULL, NULL, email)) STORED,
+  ADD UNIQUE KEY uk_users_verified_email (verified_email);
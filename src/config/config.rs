@@ -1,4 +1,79 @@
 use serde::Deserialize;
+use crate::error::FieldError;
+
+/// 私聊策略：只允许好友之间私聊，或者除被拉黑外任何人都可以私聊
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum DmPolicy {
+    FriendsOnly,
+    Anyone,
+}
+
+/// 邮件发送方式，本地开发用 log / file
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum MailerKind {
+    Log,
+    File,
+}
+
+/// 密码策略：注册、改密码、重置密码都要满足
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_letter: bool,
+    pub require_digit: bool,
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, field: &str, password: &str) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if password.chars().count() < self.min_length {
+            errors.push(FieldError::new(field, "too_short"));
+        }
+        if self.require_letter && !password.chars().any(|c| c.is_alphabetic()) {
+            errors.push(FieldError::new(field, "require_letter"));
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            errors.push(FieldError::new(field, "require_digit"));
+        }
+        errors
+    }
+}
+
+/// 登录失败限流：同一账号 / 同一 IP 在 window_secs 内失败次数超过阈值后锁定，
+/// 锁定时间从 base_lockout_secs 开始每多失败一次翻倍，最长 max_lockout_secs
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoginThrottle {
+    pub max_failures: i64,
+    pub ip_max_failures: i64,
+    pub base_lockout_secs: i64,
+    pub max_lockout_secs: i64,
+    pub window_secs: i64,
+}
+
+impl LoginThrottle {
+    /// 失败 failures 次、距最后一次失败 elapsed 秒时还需要等待的秒数，不需要等待返回 None
+    pub fn remaining_lockout(&self, failures: i64, threshold: i64, elapsed: i64) -> Option<i64> {
+        if failures < threshold {
+            return None;
+        }
+        let exponent = (failures - threshold).min(20) as u32;
+        let lockout = self.base_lockout_secs.saturating_mul(1 << exponent).min(self.max_lockout_secs);
+        (elapsed < lockout).then_some(lockout - elapsed)
+    }
+}
+
+/// OIDC 单点登录，OIDC_ISSUER 未设置时不启用
+#[derive(Debug, Deserialize, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// IdP 回调地址 (前端页面)，前端拿到 code / state 后调用 POST /auth/oidc/callback
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// 第一次 SSO 登录且没有可关联的账号时自动创建用户
+    pub auto_provision: bool,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
@@ -6,20 +81,136 @@ pub struct AppConfig {
     pub port: u16,
     pub database_url: String,
     pub jwt_secret: String,
+    pub dm_policy: DmPolicy,
+    pub upload_dir: String,
+    pub mailer: MailerKind,
+    pub mail_dir: String,
+    /// 邮件里链接指向的前端地址
+    pub app_url: String,
+    pub password_policy: PasswordPolicy,
+    /// 保留用户名 (小写)，不允许注册
+    pub reserved_usernames: Vec<String>,
+    /// 所有用户强制开启两步验证
+    pub require_two_factor: bool,
+    /// 身份验证器 App 里显示的发行方名称
+    pub totp_issuer: String,
+    pub login_throttle: LoginThrottle,
+    pub oidc: Option<OidcConfig>,
+    /// 关闭后只能通过 SSO 登录，/login、/register 不可用
+    pub password_login: bool,
+    /// 申请注销后多少天真正删除数据
+    pub account_deletion_grace_days: i64,
+    /// 回收站里的笔记保留多少天
+    pub note_trash_retention_days: i64,
+    /// 每个用户的笔记附件总空间 (字节)，配置项 ATTACHMENT_QUOTA_MB
+    pub attachment_quota_bytes: i64,
 }
 
 
+
 impl AppConfig {
     pub fn from_env() -> Result<Self, config::ConfigError> {
         let cfg = config::Config::builder()
             .add_source(config::Environment::default())
             .build()?;
+
+        let dm_policy = match cfg.get_string("dm_policy").ok().as_deref() {
+            None | Some("friends") => DmPolicy::FriendsOnly,
+            Some("anyone") => DmPolicy::Anyone,
+            Some(other) => return Err(config::ConfigError::Message(format!("invalid DM_POLICY: {}", other))),
+        };
+
+        let mailer = match cfg.get_string("mailer").ok().as_deref() {
+            None | Some("log") => MailerKind::Log,
+            Some("file") => MailerKind::File,
+            Some(other) => return Err(config::ConfigError::Message(format!("invalid MAILER: {}", other))),
+        };
         
+        let oidc = match cfg.get_string("oidc_issuer") {
+            Ok(issuer) => Some(OidcConfig {
+                issuer: issuer.trim_end_matches('/').to_string(),
+                client_id: cfg.get_string("oidc_client_id")?,
+                client_secret: cfg.get_string("oidc_client_secret").ok(),
+                redirect_uri: cfg.get_string("oidc_redirect_uri")?,
+                scopes: cfg.get_string("oidc_scopes").unwrap_or_else(|_| "openid email profile".to_string()),
+                auto_provision: cfg.get_bool("oidc_auto_provision").unwrap_or(true),
+            }),
+            Err(_) => None,
+        };
+        let password_login = cfg.get_bool("password_login").unwrap_or(true);
+        if !password_login && oidc.is_none() {
+            return Err(config::ConfigError::Message("PASSWORD_LOGIN=false requires OIDC_ISSUER".to_string()));
+        }
+
         Ok(AppConfig { 
             host: cfg.get_string("app_host")?, 
             port: cfg.get_int("app_port")? as u16, 
             database_url: cfg.get_string("database_url")?,
             jwt_secret: cfg.get_string("jwt_secret")?,
+            dm_policy,
+            upload_dir: cfg.get_string("upload_dir").unwrap_or_else(|_| "./uploads".to_string()),
+            mailer,
+            mail_dir: cfg.get_string("mail_dir").unwrap_or_else(|_| "./mail".to_string()),
+            app_url: cfg.get_string("app_url").unwrap_or_else(|_| "http://localhost:8000".to_string()),
+            password_policy: PasswordPolicy {
+                min_length: cfg.get_int("password_min_length").unwrap_or(8) as usize,
+                require_letter: cfg.get_bool("password_require_letter").unwrap_or(true),
+                require_digit: cfg.get_bool("password_require_digit").unwrap_or(true),
+            },
+            reserved_usernames: cfg.get_string("reserved_usernames")
+                .unwrap_or_else(|_| "admin,administrator,root,system,support,easynote".to_string())
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            require_two_factor: cfg.get_bool("require_two_factor").unwrap_or(false),
+            totp_issuer: cfg.get_string("totp_issuer").unwrap_or_else(|_| "EasyNote".to_string()),
+            login_throttle: LoginThrottle {
+                max_failures: cfg.get_int("login_max_failures").unwrap_or(5),
+                ip_max_failures: cfg.get_int("login_ip_max_failures").unwrap_or(20),
+                base_lockout_secs: cfg.get_int("login_lockout_secs").unwrap_or(60),
+                max_lockout_secs: cfg.get_int("login_max_lockout_secs").unwrap_or(3600),
+                window_secs: cfg.get_int("login_window_secs").unwrap_or(3600),
+            },
+            oidc,
+            password_login,
+            account_deletion_grace_days: cfg.get_int("account_deletion_grace_days").unwrap_or(14),
+            note_trash_retention_days: cfg.get_int("note_trash_retention_days").unwrap_or(30),
+            attachment_quota_bytes: cfg.get_int("attachment_quota_mb").unwrap_or(500) * 1024 * 1024,
         })
     }
-}
\ No newline at end of file
+
+    pub fn is_reserved_username(&self, username: &str) -> bool {
+        self.reserved_usernames.contains(&username.to_lowercase())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::PasswordPolicy;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy { min_length: 8, require_letter: true, require_digit: true }
+    }
+
+    fn codes(password: &str) -> Vec<String> {
+        policy().check("password", password).into_iter().map(|e| e.code).collect()
+    }
+
+    #[test]
+    fn accepts_compliant_password() {
+        assert!(codes("abcdefg1").is_empty());
+    }
+
+    #[test]
+    fn min_length_counts_characters() {
+        assert_eq!(codes("abcdef1"), vec!["too_short"]);
+        // 多字节字符按字符计数
+        assert!(codes("密码密码密码a1").is_empty());
+    }
+
+    #[test]
+    fn requires_letter_and_digit() {
+        assert_eq!(codes("12345678"), vec!["require_letter"]);
+        assert_eq!(codes("abcdefgh"), vec!["require_digit"]);
+    }
+}
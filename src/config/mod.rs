@@ -1,3 +1,3 @@
 mod config;
 
-pub use config::AppConfig;
\ No newline at end of file
+pub use config::{AppConfig, DmPolicy, MailerKind, LoginThrottle, OidcConfig};
\ No newline at end of file
@@ -3,12 +3,17 @@ use serde::Serialize;
 use std::fmt;
 
 #[derive(Debug, Serialize)]
-#[allow(dead_code)]
 pub struct FieldError {
     pub field: String,
     pub code: String,
 }
 
+impl FieldError {
+    pub fn new(field: &str, code: &str) -> Self {
+        FieldError { field: field.to_string(), code: code.to_string() }
+    }
+}
+
 
 #[derive(Debug)]
 pub enum AppError {
@@ -16,10 +21,44 @@ pub enum AppError {
     BadRequest(String),
     Internal(String),
     Unauthorized(String),
-    #[allow(dead_code)]
+    Forbidden(String),
+    /// 请求过于频繁，参数是建议的重试等待秒数
+    TooManyRequests(i64),
     Validation(Vec<FieldError>)
 }
 
+/// DTO 上 `#[derive(Validate)]` 的校验结果转成 422
+impl From<validator::ValidationErrors> for AppError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let mut fields: Vec<FieldError> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errs)| errs.iter().map(move |e| FieldError::new(&field, &e.code)))
+            .collect();
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::Validation(fields)
+    }
+}
+
+impl AppError {
+    /// 合并 DTO 校验结果和额外的字段错误 (密码策略、保留用户名等)，一次返回全部错误
+    pub fn validate(result: Result<(), validator::ValidationErrors>, extra: Vec<FieldError>) -> Result<(), AppError> {
+        let mut fields = match result {
+            Ok(()) => Vec::new(),
+            Err(errors) => match AppError::from(errors) {
+                AppError::Validation(fields) => fields,
+                other => return Err(other),
+            },
+        };
+        fields.extend(extra);
+        if fields.is_empty() {
+            return Ok(());
+        }
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        Err(AppError::Validation(fields))
+    }
+}
+
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -27,6 +66,8 @@ impl fmt::Display for AppError {
             AppError::BadRequest(msg) => write!(f, "Bad Request: {}", msg),
             AppError::Internal(msg) => write!(f, "Internal Error: {}", msg),
             AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::TooManyRequests(secs) => write!(f, "Too Many Requests: retry after {}s", secs),
             AppError::Validation(errors) => write!(f, "Validation Failed: {} errors", errors.len()),
         }
     }
@@ -60,6 +101,20 @@ impl ResponseError for AppError {
                     "message": msg
                 }))
             }
+            AppError::Forbidden(msg) => {
+                HttpResponse::Forbidden().json(serde_json::json!({
+                    "code": 403,
+                    "message": msg
+                }))
+            }
+            AppError::TooManyRequests(secs) => {
+                HttpResponse::TooManyRequests()
+                    .insert_header(("Retry-After", secs.to_string()))
+                    .json(serde_json::json!({
+                        "code": 429,
+                        "message": format!("尝试次数过多，请 {} 秒后再试", secs)
+                    }))
+            }
             AppError::Validation(errors) => {
                 HttpResponse::UnprocessableEntity().json(serde_json::json!({
                     "msg": "Validation Failed",
@@ -1,3 +1,3 @@
 mod error;
 
-pub use error::AppError;
+pub use error::{AppError, FieldError};
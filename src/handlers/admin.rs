@@ -0,0 +1,204 @@
+use std::env;
+
+use actix::Addr;
+use actix_web::{HttpResponse, web::{self, ServiceConfig}};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{error::AppError, middleware::{Auth, RequireRole}, models::{CreateTemplate, Role, UpdateTemplate}, services::{AdminService, TemplateService, UserFilter, UserService}, utils::Claims};
+use crate::handlers::{ChatServer, Kick};
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUserQuery {
+  q: Option<String>,
+  role: Option<Role>,
+  disabled: Option<bool>,
+  limit: Option<i64>,
+  offset: Option<i64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+  user_id: Option<i64>,
+  limit: Option<i64>,
+  offset: Option<i64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRoleReq {
+  role: Role
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisableReq {
+  #[validate(length(max = 255))]
+  reason: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorRequiredReq {
+  required: bool
+}
+
+pub async fn list_users(pool: web::Data<MySqlPool>, query: web::Query<AdminUserQuery>) -> Result<HttpResponse, AppError> {
+  let filter = UserFilter {
+    q: query.q.as_deref(),
+    role: query.role,
+    disabled: query.disabled,
+    limit: query.limit.unwrap_or(50).clamp(1, 200),
+    offset: query.offset.unwrap_or(0).max(0),
+  };
+  let result = AdminService::list_users(pool.get_ref(), filter).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+pub async fn get_user(pool: web::Data<MySqlPool>, path: web::Path<i64>) -> Result<HttpResponse, AppError> {
+  let user = UserService::find_by_id(pool.get_ref(), path.into_inner()).await?;
+  Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn set_role(
+  pool: web::Data<MySqlPool>,
+  path: web::Path<i64>,
+  body: web::Json<SetRoleReq>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let user = AdminService::set_role(pool.get_ref(), claims.sub, path.into_inner(), body.role).await?;
+  Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn disable_user(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  body: web::Json<DisableReq>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let user = AdminService::disable(pool.get_ref(), claims.sub, path.into_inner(), body.reason.as_deref()).await?;
+  server.do_send(Kick { user_id: user.id, reason: "账号已被禁用".to_string() });
+  Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn enable_user(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let user = AdminService::enable(pool.get_ref(), claims.sub, path.into_inner()).await?;
+  Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn force_logout(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let user_id = path.into_inner();
+  AdminService::force_logout(pool.get_ref(), claims.sub, user_id).await?;
+  server.do_send(Kick { user_id, reason: "已被管理员强制下线".to_string() });
+  Ok(HttpResponse::NoContent().finish())
+}
+
+/// 返回临时密码，由管理员转交给用户
+pub async fn reset_password(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let user_id = path.into_inner();
+  let password = AdminService::reset_password(pool.get_ref(), claims.sub, user_id).await?;
+  server.do_send(Kick { user_id, reason: "密码已被管理员重置".to_string() });
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "temporary_password": password
+  })))
+}
+
+pub async fn set_two_factor_required(
+  pool: web::Data<MySqlPool>,
+  path: web::Path<i64>,
+  body: web::Json<TwoFactorRequiredReq>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let user = AdminService::set_two_factor_required(pool.get_ref(), claims.sub, path.into_inner(), body.required).await?;
+  Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn delete_user_content(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let (notes, messages) = AdminService::delete_user_content(pool.get_ref(), claims.sub, path.into_inner()).await?;
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "deleted_notes": notes,
+    "deleted_messages": messages
+  })))
+}
+
+pub async fn delete_note(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  AdminService::delete_note(pool.get_ref(), claims.sub, path.into_inner()).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_templates(pool: web::Data<MySqlPool>) -> Result<HttpResponse, AppError> {
+  let templates = TemplateService::find_global(pool.get_ref()).await?;
+  Ok(HttpResponse::Ok().json(templates))
+}
+
+/// 全局模板，所有用户都能用
+pub async fn create_template(
+  pool: web::Data<MySqlPool>,
+  body: web::Json<CreateTemplate>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let template = AdminService::create_template(pool.get_ref(), claims.sub, body.into_inner()).await?;
+  Ok(HttpResponse::Created().json(template))
+}
+
+pub async fn update_template(
+  pool: web::Data<MySqlPool>,
+  path: web::Path<i64>,
+  body: web::Json<UpdateTemplate>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let template = AdminService::update_template(pool.get_ref(), claims.sub, path.into_inner(), body.into_inner()).await?;
+  Ok(HttpResponse::Ok().json(template))
+}
+
+pub async fn delete_template(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  AdminService::delete_template(pool.get_ref(), claims.sub, path.into_inner()).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_audit(pool: web::Data<MySqlPool>, query: web::Query<AuditQuery>) -> Result<HttpResponse, AppError> {
+  let limit = query.limit.unwrap_or(50).clamp(1, 200);
+  let offset = query.offset.unwrap_or(0).max(0);
+  let result = AdminService::list_actions(pool.get_ref(), query.user_id, limit, offset).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// RequireRole 先 wrap，Auth 后 wrap (外层)，请求先经过 Auth 加载角色
+pub fn configure(cfg: &mut ServiceConfig) {
+  let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
+
+  cfg.service(
+    web::scope("/admin")
+    .wrap(RequireRole(Role::Admin))
+    .wrap(Auth { jwt_secret, scope: None })
+    .route("/users", web::get().to(list_users))
+    .route("/users/{id}", web::get().to(get_user))
+    .route("/users/{id}/role", web::put().to(set_role))
+    .route("/users/{id}/disable", web::post().to(disable_user))
+    .route("/users/{id}/enable", web::post().to(enable_user))
+    .route("/users/{id}/logout", web::post().to(force_logout))
+    .route("/users/{id}/password", web::post().to(reset_password))
+    .route("/users/{id}/two-factor", web::put().to(set_two_factor_required))
+    .route("/users/{id}/content", web::delete().to(delete_user_content))
+    .route("/notes/{id}", web::delete().to(delete_note))
+    .route("/templates", web::get().to(list_templates))
+    .route("/templates", web::post().to(create_template))
+    .route("/templates/{id}", web::put().to(update_template))
+    .route("/templates/{id}", web::delete().to(delete_template))
+    .route("/audit", web::get().to(list_audit))
+  );
+}
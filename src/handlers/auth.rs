@@ -1,31 +1,115 @@
-use actix_web::{web, HttpResponse};
+use actix::Addr;
+use actix_web::{web, HttpRequest, HttpResponse};
 use serde::Deserialize;
+use validator::Validate;
 use sqlx::{MySqlPool};
 use crate::config::AppConfig;
-use crate::error::AppError;
-use crate::services::UserService;
-use crate::utils::JwtUtil;
-use crate::models::RegisterRequest;
+use crate::error::{AppError, FieldError};
+use crate::handlers::{ChatServer, Kick};
+use crate::services::{AccountService, LoginFailure, LoginGuard, SsoService, TwoFactorService, UserService};
+use crate::utils::{JwtUtil, Mailer, OidcClient};
+use crate::models::{RegisterRequest, ForgotPassword, ResetPassword, VerifyEmail, User, validate_password};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(length(min = 1, max = 32))]
     pub username: String,
+    #[validate(length(min = 1), custom(function = "validate_password"))]
     pub password: String,
 }
 
+/// 两步验证的 challenge 用途：已开启的用户校验验证码，被强制要求的用户先绑定
+const CHALLENGE_VERIFY: &str = "2fa_verify";
+const CHALLENGE_SETUP: &str = "2fa_setup";
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChallengeRequest {
+    #[validate(length(min = 1))]
+    pub challenge_token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct OidcCallbackRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+    #[validate(length(min = 1))]
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChallengeCodeRequest {
+    #[validate(length(min = 1))]
+    pub challenge_token: String,
+    #[validate(length(min = 6, max = 16))]
+    pub code: String,
+}
+
+/// 用对端地址而不是 X-Forwarded-For，避免伪造 IP 绕过限流
+fn client_ip(req: &HttpRequest) -> String {
+    req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string())
+}
+
 async fn login(
+    req: HttpRequest,
     pool: web::Data<MySqlPool>,
     config: web::Data<AppConfig>,
     body: web::Json<LoginRequest>,
 ) -> Result<HttpResponse, AppError> {
-    let user = UserService::find_by_username(pool.get_ref(), &body.username).await?;
+    if !config.password_login {
+        return Err(AppError::Forbidden("请使用单点登录".to_string()));
+    }
+    body.validate()?;
+    let ip = client_ip(&req);
+    LoginGuard::check(pool.get_ref(), &config.login_throttle, &body.username, &ip).await?;
+
+    // 用户不存在和密码错误返回同样的错误，耗时也一样
+    let user = match UserService::find_by_username(pool.get_ref(), &body.username).await {
+        Ok(user) => user,
+        Err(AppError::NotFound(_)) => {
+            UserService::verify_dummy_password(&body.password);
+            LoginGuard::record_failure(pool.get_ref(), &body.username, None, &ip, LoginFailure::UnknownUser).await?;
+            return Err(AppError::Unauthorized("用户名或密码错误".to_string()));
+        }
+        Err(e) => return Err(e),
+    };
 
     let is_valid = UserService::verify_password(&body.password, &user.password_hash)?;
     if !is_valid {
+        LoginGuard::record_failure(pool.get_ref(), &body.username, Some(user.id), &ip, LoginFailure::BadPassword).await?;
         return Err(AppError::Unauthorized("用户名或密码错误".to_string()));
     }
+    finish_login(pool.get_ref(), &config, user, &ip).await
+}
+
+/// 密码或 SSO 验证通过之后的公共步骤
+async fn finish_login(pool: &MySqlPool, config: &AppConfig, user: User, ip: &str) -> Result<HttpResponse, AppError> {
+    if user.disabled_at.is_some() {
+        return Err(AppError::Forbidden("账号已被禁用".to_string()));
+    }
+
+    // 开启了两步验证 (或被要求开启) 时先不发正式 token，返回 5 分钟有效的 challenge_token
+    let (two_factor, purpose) = if user.totp_enabled_at.is_some() {
+        ("required", CHALLENGE_VERIFY)
+    } else if config.require_two_factor || user.two_factor_required {
+        ("setup_required", CHALLENGE_SETUP)
+    } else {
+        LoginGuard::record_success(pool, &user.username, user.id, ip).await?;
+        return issue_token(config, user);
+    };
+
+    let challenge_token = JwtUtil::generate_challenge(user.id, user.token_version, purpose, &config.jwt_secret)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "two_factor": two_factor,
+        "challenge_token": challenge_token
+    })))
+}
 
-    let token = JwtUtil::generate_token(user.id, &config.jwt_secret)?;
+fn issue_token(config: &AppConfig, user: User) -> Result<HttpResponse, AppError> {
+    if user.disabled_at.is_some() {
+        return Err(AppError::Forbidden("账号已被禁用".to_string()));
+    }
+    let token = JwtUtil::generate_token(user.id, user.token_version, &config.jwt_secret)?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "code": 200,
@@ -34,11 +118,139 @@ async fn login(
     })))
 }
 
+/// challenge_token 签发后改过密码 (token_version 变了) 也视为失效
+async fn challenge_user(pool: &MySqlPool, config: &AppConfig, challenge_token: &str, purpose: &str) -> Result<User, AppError> {
+    let claims = JwtUtil::verify_challenge(challenge_token, purpose, &config.jwt_secret)?;
+    let user = UserService::find_by_id(pool, claims.sub).await?;
+    if user.token_version != claims.ver {
+        return Err(AppError::Unauthorized("验证已过期，请重新登录".to_string()));
+    }
+    Ok(user)
+}
+
+/// 登录第二步：TOTP 验证码或恢复码，验证码错误和密码错误一起计入失败次数
+async fn login_two_factor(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    body: web::Json<ChallengeCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let user = challenge_user(pool.get_ref(), &config, &body.challenge_token, CHALLENGE_VERIFY).await?;
+    let ip = client_ip(&req);
+    LoginGuard::check(pool.get_ref(), &config.login_throttle, &user.username, &ip).await?;
+
+    if let Err(e) = TwoFactorService::verify(pool.get_ref(), &user, &body.code).await {
+        if matches!(e, AppError::Unauthorized(_)) {
+            LoginGuard::record_failure(pool.get_ref(), &user.username, Some(user.id), &ip, LoginFailure::BadTwoFactor).await?;
+        }
+        return Err(e);
+    }
+    LoginGuard::record_success(pool.get_ref(), &user.username, user.id, &ip).await?;
+    issue_token(&config, user)
+}
+
+/// 被强制要求两步验证但还没绑定的用户，登录时先获取密钥
+async fn login_two_factor_setup(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    body: web::Json<ChallengeRequest>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let user = challenge_user(pool.get_ref(), &config, &body.challenge_token, CHALLENGE_SETUP).await?;
+    let setup = TwoFactorService::begin_enrollment(pool.get_ref(), &user, &config.totp_issuer).await?;
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+/// 确认绑定后直接登录，同时返回恢复码
+async fn login_two_factor_confirm(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    body: web::Json<ChallengeCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let user = challenge_user(pool.get_ref(), &config, &body.challenge_token, CHALLENGE_SETUP).await?;
+    let ip = client_ip(&req);
+    LoginGuard::check(pool.get_ref(), &config.login_throttle, &user.username, &ip).await?;
+
+    let recovery_codes = match TwoFactorService::confirm_enrollment(pool.get_ref(), user.id, &body.code).await {
+        Ok(codes) => codes,
+        Err(e) => {
+            if matches!(e, AppError::Unauthorized(_)) {
+                LoginGuard::record_failure(pool.get_ref(), &user.username, Some(user.id), &ip, LoginFailure::BadTwoFactor).await?;
+            }
+            return Err(e);
+        }
+    };
+    LoginGuard::record_success(pool.get_ref(), &user.username, user.id, &ip).await?;
+    let token = JwtUtil::generate_token(user.id, user.token_version, &config.jwt_secret)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "token": token,
+        "recovery_codes": recovery_codes,
+        "data": UserService::find_by_id(pool.get_ref(), user.id).await?
+    })))
+}
+
+/// 未配置 OIDC 时 OidcClient 不会注册到 app_data
+pub fn oidc_client(client: Option<web::Data<OidcClient>>) -> Result<web::Data<OidcClient>, AppError> {
+    client.ok_or_else(|| AppError::NotFound("未启用单点登录".to_string()))
+}
+
+/// 返回 IdP 授权地址，前端跳转过去
+async fn oidc_authorize(
+    pool: web::Data<MySqlPool>,
+    client: Option<web::Data<OidcClient>>,
+) -> Result<HttpResponse, AppError> {
+    let client = oidc_client(client)?;
+    let authorization_url = SsoService::begin(pool.get_ref(), &client, None).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "authorization_url": authorization_url
+    })))
+}
+
+/// IdP 回调到前端后，前端把 code / state 交给这里换取我们自己的 JWT
+/// 本地开启或被要求两步验证的用户和密码登录一样先返回 challenge_token
+async fn oidc_callback(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    client: Option<web::Data<OidcClient>>,
+    body: web::Json<OidcCallbackRequest>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let client = oidc_client(client)?;
+    let user = SsoService::complete(pool.get_ref(), &client, &body.code, &body.state, &config.reserved_usernames).await?;
+    finish_login(pool.get_ref(), &config, user, &client_ip(&req)).await
+}
+
 async fn register(
     pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    mailer: web::Data<dyn Mailer>,
     body: web::Json<RegisterRequest>
 ) -> Result<HttpResponse, AppError> {
+    if !config.password_login {
+        return Err(AppError::Forbidden("请使用单点登录".to_string()));
+    }
+    let mut extra = config.password_policy.check("password", &body.password);
+    if config.is_reserved_username(&body.username) {
+        extra.push(FieldError::new("username", "reserved"));
+    }
+    AppError::validate(body.validate(), extra)?;
+
     let user = UserService::register(pool.get_ref(), body.into_inner()).await?;
+
+    // 验证邮件发送失败不影响注册，用户可以之后重新发送
+    if user.email.is_some()
+        && let Err(e) = AccountService::send_email_verification(pool.get_ref(), mailer.get_ref(), &config.app_url, &user).await
+    {
+        tracing::warn!("验证邮件发送失败：{}", e);
+    }
+
     Ok(HttpResponse::Created().json(serde_json::json!({
         "code": 201,
         "message": "注册成功",
@@ -46,7 +258,62 @@ async fn register(
     })))
 }
 
+async fn forgot_password(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    mailer: web::Data<dyn Mailer>,
+    body: web::Json<ForgotPassword>
+) -> Result<HttpResponse, AppError> {
+    if !config.password_login {
+        return Err(AppError::Forbidden("请使用单点登录".to_string()));
+    }
+    body.validate()?;
+    AccountService::request_password_reset(pool.get_ref(), mailer.get_ref(), &config.app_url, &body.email).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "message": "如果该邮箱已注册，重置链接已发送"
+    })))
+}
+
+async fn reset_password(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    server: web::Data<Addr<ChatServer>>,
+    body: web::Json<ResetPassword>
+) -> Result<HttpResponse, AppError> {
+    if !config.password_login {
+        return Err(AppError::Forbidden("请使用单点登录".to_string()));
+    }
+    AppError::validate(body.validate(), config.password_policy.check("new_password", &body.new_password))?;
+    let user_id = AccountService::reset_password(pool.get_ref(), &body.token, &body.new_password).await?;
+    server.do_send(Kick { user_id, reason: "密码已重置，请重新登录".to_string() });
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "message": "密码已重置，请重新登录"
+    })))
+}
+
+async fn verify_email(
+    pool: web::Data<MySqlPool>,
+    body: web::Json<VerifyEmail>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    AccountService::verify_email(pool.get_ref(), &body.token).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "message": "邮箱验证成功"
+    })))
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.route("/login", web::post().to(login))
-        .route("/register", web::post().to(register));
+        .route("/login/2fa", web::post().to(login_two_factor))
+        .route("/login/2fa/setup", web::post().to(login_two_factor_setup))
+        .route("/login/2fa/confirm", web::post().to(login_two_factor_confirm))
+        .route("/auth/oidc/authorize", web::get().to(oidc_authorize))
+        .route("/auth/oidc/callback", web::post().to(oidc_callback))
+        .route("/register", web::post().to(register))
+        .route("/password/forgot", web::post().to(forgot_password))
+        .route("/password/reset", web::post().to(reset_password))
+        .route("/verify-email", web::post().to(verify_email));
 }
\ No newline at end of file
@@ -1,18 +1,29 @@
+use actix::Addr;
 use actix_web::{HttpResponse, web};
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
 use std::env;
+use validator::Validate;
 
+use crate::config::AppConfig;
 use crate::error::AppError;
-use crate::middleware::Auth;
-use crate::models::MemberRole;
-use crate::services::ConversationServices;
+use crate::middleware::{Auth, ApiScope};
+use crate::handlers::{ Broadcast, ChatServer };
+use crate::models::{ MemberRole, UpdateMemberSettings, ShareNoteToConversation, UserProfile };
+use crate::services::{ ConversationServices, ConversationNoteService, FriendLabelService, UserService };
 use crate::utils::Claims;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateConversationReq {
+  #[validate(length(min = 1, max = 64))]
   name: Option<String>,
-  member_ids: Vec<i64>
+  #[serde(default)]
+  #[validate(length(max = 500))]
+  member_ids: Vec<i64>,
+  /// 好友标签，展开为标签内的好友一起加入会话
+  #[serde(default)]
+  #[validate(length(max = 50))]
+  label_ids: Vec<i64>
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,14 +31,33 @@ pub struct AddMemberReq {
   user_id: i64
 }
 
-pub async fn create(pool: web::Data<MySqlPool>, body: web::Json<CreateConversationReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
-  let CreateConversationReq {name, member_ids} = body.into_inner();
-  let conv = ConversationServices::create(pool.get_ref(), claims.sub, name, member_ids).await?;
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+  #[serde(default)]
+  archived: bool,
+  limit: Option<i64>,
+  offset: Option<i64>
+}
+
+pub async fn create(pool: web::Data<MySqlPool>, config: web::Data<AppConfig>, body: web::Json<CreateConversationReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let CreateConversationReq {name, mut member_ids, label_ids} = body.into_inner();
+  member_ids.extend(FriendLabelService::resolve_members(pool.get_ref(), claims.sub, &label_ids).await?);
+  member_ids.retain(|id| *id != claims.sub);
+  member_ids.sort_unstable();
+  member_ids.dedup();
+  if member_ids.is_empty() {
+    return Err(AppError::BadRequest("会话成员不能为空".to_string()));
+  }
+  let conv = ConversationServices::create(pool.get_ref(), claims.sub, name, member_ids, config.dm_policy).await?;
   Ok(HttpResponse::Created().json(conv))
 }
 
-pub async fn list(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
-  let list = ConversationServices::get_user_conversations(pool.get_ref(), claims.sub).await?;
+pub async fn list(pool: web::Data<MySqlPool>, query: web::Query<ListQuery>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let ListQuery { archived, limit, offset } = query.into_inner();
+  let limit = limit.unwrap_or(50).clamp(1, 200);
+  let offset = offset.unwrap_or(0).max(0);
+  let list = ConversationServices::get_user_conversations(pool.get_ref(), claims.sub, archived, limit, offset).await?;
   Ok(HttpResponse::Ok().json(list))
 }
 
@@ -38,15 +68,68 @@ pub async fn add_member(pool: web::Data<MySqlPool>, body: web::Json<AddMemberReq
   Ok(HttpResponse::Ok().json(result))
 }
 
+pub async fn update_settings(pool: web::Data<MySqlPool>, body: web::Json<UpdateMemberSettings>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let conversation_id = path.into_inner();
+  ConversationServices::update_settings(pool.get_ref(), conversation_id, claims.sub, body.into_inner()).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn clear_history(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let conversation_id = path.into_inner();
+  ConversationServices::clear_history(pool.get_ref(), conversation_id, claims.sub).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+/// 会话里分享过的笔记
+pub async fn list_notes(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let notes = ConversationNoteService::list(pool.get_ref(), path.into_inner(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(notes))
+}
+
+/// 以卡片消息的形式分享笔记，推送给房间内的在线成员
+pub async fn share_note(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  body: web::Json<ShareNoteToConversation>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let conversation_id = path.into_inner();
+  let (content, card) = ConversationNoteService::share(pool.get_ref(), conversation_id, claims.sub, body.note_id).await?;
+  let sender = UserProfile::from(UserService::find_by_id(pool.get_ref(), claims.sub).await?);
+
+  let message = serde_json::json!({
+    "type": "note",
+    "conversation_id": conversation_id,
+    "sender": sender,
+    "content": content,
+    "note": card,
+    "created_at": chrono::Utc::now()
+  });
+  server.do_send(Broadcast { conversation_id, sender_id: claims.sub, msg: message.to_string() });
+  Ok(HttpResponse::Created().json(message))
+}
+
+/// 取消分享，只有笔记作者可以操作
+pub async fn unshare_note(pool: web::Data<MySqlPool>, path: web::Path<(i64, i64)>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let (conversation_id, note_id) = path.into_inner();
+  ConversationNoteService::unshare(pool.get_ref(), conversation_id, claims.sub, note_id).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
   let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
 
   cfg.service(
     web::scope("/conversations")
-    .wrap(Auth {jwt_secret })
+    .wrap(Auth { jwt_secret, scope: Some(ApiScope::CHAT) })
     .route("", web::post().to(create))
     .route("", web::get().to(list))
     .route("/{conversation_id}/members", web::post().to(add_member))
+    .route("/{conversation_id}/settings", web::put().to(update_settings))
+    .route("/{conversation_id}/clear", web::post().to(clear_history))
+    .route("/{conversation_id}/notes", web::get().to(list_notes))
+    .route("/{conversation_id}/notes", web::post().to(share_note))
+    .route("/{conversation_id}/notes/{note_id}", web::delete().to(unshare_note))
   );
 }
\ No newline at end of file
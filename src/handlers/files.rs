@@ -0,0 +1,44 @@
+use actix_web::{HttpResponse, web};
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::services::AttachmentService;
+use crate::utils::Storage;
+
+/// 头像公开访问，不需要登录
+async fn get_avatar(storage: web::Data<Storage>, path: web::Path<String>) -> Result<HttpResponse, AppError> {
+  let key = format!("avatars/{}", path.into_inner());
+  let data = storage.load(&key).await?;
+
+  Ok(HttpResponse::Ok()
+    .content_type(Storage::content_type(&key))
+    .insert_header(("Cache-Control", "public, max-age=86400"))
+    .body(data))
+}
+
+/// 笔记附件，地址里的 token 不可猜测，不需要登录 (Markdown 里的图片才能直接显示)
+/// 可能执行脚本的类型一律作为下载返回
+async fn get_attachment(
+  pool: web::Data<MySqlPool>,
+  storage: web::Data<Storage>,
+  path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+  let attachment = AttachmentService::find_by_token(pool.get_ref(), &path.into_inner()).await?;
+  let data = storage.load(&attachment.storage_key).await?;
+  let inline = Storage::is_inline_safe(&attachment.content_type);
+
+  Ok(HttpResponse::Ok()
+    .content_type(if inline { attachment.content_type.as_str() } else { "application/octet-stream" })
+    .insert_header(Storage::content_disposition(inline, &attachment.filename))
+    .insert_header(("X-Content-Type-Options", "nosniff"))
+    .insert_header(("Cache-Control", "private, max-age=3600"))
+    .body(data))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+  cfg.service(
+    web::scope("/files")
+    .route("/avatars/{name}", web::get().to(get_avatar))
+    .route("/attachments/{token}", web::get().to(get_attachment))
+  );
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+use actix_web::{web, HttpResponse};
+use sqlx::MySqlPool;
+use std::env;
+use validator::Validate;
+use crate::error::AppError;
+use crate::middleware::{Auth, ApiScope};
+use crate::models::{CreateFolder, RenameFolder, MoveTarget, ShareNote};
+use crate::services::{FolderService, FriendLabelService};
+use crate::utils::Claims;
+
+async fn list(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let folders = FolderService::find_all(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(folders))
+}
+
+/// 根目录：顶层文件夹 + 未归档的笔记
+async fn root_contents(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let contents = FolderService::contents(pool.get_ref(), None, claims.sub).await?;
+    Ok(HttpResponse::Ok().json(contents))
+}
+
+async fn contents(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let contents = FolderService::contents(pool.get_ref(), Some(path.into_inner()), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(contents))
+}
+
+async fn create(
+    pool: web::Data<MySqlPool>,
+    body: web::Json<CreateFolder>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let folder = FolderService::create(pool.get_ref(), body.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::Created().json(folder))
+}
+
+async fn rename(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<RenameFolder>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let folder = FolderService::rename(pool.get_ref(), path.into_inner(), claims.sub, &body.name).await?;
+    Ok(HttpResponse::Ok().json(folder))
+}
+
+async fn move_to(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<MoveTarget>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let folder = FolderService::move_to(pool.get_ref(), path.into_inner(), claims.sub, body.folder_id).await?;
+    Ok(HttpResponse::Ok().json(folder))
+}
+
+async fn delete(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    FolderService::delete(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn list_shared(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let folders = FolderService::find_shared_with(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(folders))
+}
+
+async fn share(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<ShareNote>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let id = path.into_inner();
+    let ShareNote { mut user_ids, label_ids } = body.into_inner();
+    user_ids.extend(FriendLabelService::resolve_members(pool.get_ref(), claims.sub, &label_ids).await?);
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    FolderService::share(pool.get_ref(), id, claims.sub, &user_ids).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn unshare(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i64, i64)>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let (id, user_id) = path.into_inner();
+    FolderService::unshare(pool.get_ref(), id, claims.sub, user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
+
+    cfg.service(
+        web::scope("/folders")
+            .wrap(Auth { jwt_secret, scope: Some(ApiScope::NOTES) })
+            .route("", web::get().to(list))
+            .route("", web::post().to(create))
+            .route("/root", web::get().to(root_contents))
+            .route("/shared", web::get().to(list_shared))
+            .route("/{id}", web::get().to(contents))
+            .route("/{id}", web::put().to(rename))
+            .route("/{id}", web::delete().to(delete))
+            .route("/{id}/move", web::post().to(move_to))
+            .route("/{id}/shares", web::post().to(share))
+            .route("/{id}/shares/{user_id}", web::delete().to(unshare))
+    );
+}
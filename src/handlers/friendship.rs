@@ -1,27 +1,62 @@
 use std::env;
 
+use actix::Addr;
 use actix_web::{ HttpResponse, web};
 use serde::{ Deserialize, Serialize };
 use sqlx::MySqlPool;
+use validator::Validate;
 
-use crate::{error::AppError, middleware::Auth, services::FriendShipService, utils::Claims};
+use crate::{error::AppError, middleware::Auth, models::{FriendShip, FriendShipStatus, LabelMembersReq, LabelReq, UserProfile}, services::{FriendLabelService, FriendShipService, UserService}, utils::Claims};
+use crate::handlers::{ChatServer, Notify};
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct FriendShipReq {
-  receiver_id: i64
+  receiver_id: i64,
+  #[validate(length(max = 255))]
+  message: Option<String>
 }
 
-async fn send_friendship_request(pool: web::Data<MySqlPool>, body: web::Json<FriendShipReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
-  let FriendShipReq { receiver_id} = body.into_inner();
-  let result = FriendShipService::send_request(pool.get_ref(), claims.sub, receiver_id).await?;
+#[derive(Debug, Deserialize)]
+pub struct FriendListQuery {
+  label_id: Option<i64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionQuery {
+  limit: Option<i64>
+}
+
+/// 通过 ChatServer 给在线用户推送好友事件，user 是触发事件的一方
+fn notify(server: &Addr<ChatServer>, user_id: i64, event: &str, friendship: &FriendShip, user: &UserProfile) {
+  let msg = serde_json::json!({
+    "type": event,
+    "data": friendship,
+    "user": user
+  }).to_string();
+  server.do_send(Notify { user_id, msg });
+}
+
+async fn send_friendship_request(pool: web::Data<MySqlPool>, server: web::Data<Addr<ChatServer>>, body: web::Json<FriendShipReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let FriendShipReq { receiver_id, message } = body.into_inner();
+  let result = FriendShipService::send_request(pool.get_ref(), claims.sub, receiver_id, message).await?;
+  let me = UserService::find_profile(pool.get_ref(), claims.sub).await?;
+
+  match result.status {
+    // 对方之前也发过请求，已自动成为好友
+    FriendShipStatus::Accepted => notify(&server, result.requester_id, "friend_accepted", &result, &me),
+    _ => notify(&server, result.receiver_id, "friend_request", &result, &me),
+  }
 
   Ok(HttpResponse::Ok().json(result))
 }
 
-async fn accept_friendship(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+async fn accept_friendship(pool: web::Data<MySqlPool>, server: web::Data<Addr<ChatServer>>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
   let friendship_id = path.into_inner();
   let result = FriendShipService::accept(pool.get_ref(), friendship_id, claims.sub).await?;
+  let me = UserService::find_profile(pool.get_ref(), claims.sub).await?;
+  notify(&server, result.requester_id, "friend_accepted", &result, &me);
 
   Ok(HttpResponse::Ok().json(result))
 }
@@ -33,26 +68,103 @@ async fn reject_friendship(pool: web::Data<MySqlPool>, path: web::Path<i64>, cla
   Ok(HttpResponse::Ok().json(result))
 }
 
+async fn cancel_friendship(pool: web::Data<MySqlPool>, server: web::Data<Addr<ChatServer>>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let friendship_id = path.into_inner();
+  let result = FriendShipService::cancel(pool.get_ref(), friendship_id, claims.sub).await?;
+  let me = UserService::find_profile(pool.get_ref(), claims.sub).await?;
+  notify(&server, result.receiver_id, "friend_request_cancelled", &result, &me);
+
+  Ok(HttpResponse::NoContent().finish())
+}
+
+async fn remove_friend(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let friend_id = path.into_inner();
+  FriendShipService::remove_friend(pool.get_ref(), claims.sub, friend_id).await?;
+
+  Ok(HttpResponse::NoContent().finish())
+}
+
 async fn list_pending(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
   let result = FriendShipService::list_pending(pool.get_ref(), claims.sub).await?;
   Ok(HttpResponse::Ok().json(result))
 }
 
-async fn list_friends(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
-  let result = FriendShipService::list_friends(pool.get_ref(), claims.sub).await?;
+async fn list_outgoing(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let result = FriendShipService::list_outgoing(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+async fn list_friends(pool: web::Data<MySqlPool>, query: web::Query<FriendListQuery>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  if let Some(label_id) = query.label_id {
+    FriendLabelService::find_owned(pool.get_ref(), label_id, claims.sub).await?;
+  }
+  let result = FriendShipService::list_friends(pool.get_ref(), claims.sub, query.label_id).await?;
   Ok(HttpResponse::Ok().json(result))
 }
 
+async fn list_suggestions(pool: web::Data<MySqlPool>, query: web::Query<SuggestionQuery>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let limit = query.limit.unwrap_or(20).clamp(1, 100);
+  let result = FriendShipService::suggestions(pool.get_ref(), claims.sub, limit).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+async fn list_labels(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let result = FriendLabelService::list(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+async fn create_label(pool: web::Data<MySqlPool>, body: web::Json<LabelReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let result = FriendLabelService::create(pool.get_ref(), claims.sub, &body.name).await?;
+  Ok(HttpResponse::Created().json(result))
+}
+
+async fn rename_label(pool: web::Data<MySqlPool>, path: web::Path<i64>, body: web::Json<LabelReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let label_id = path.into_inner();
+  let result = FriendLabelService::rename(pool.get_ref(), label_id, claims.sub, &body.name).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+async fn delete_label(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let label_id = path.into_inner();
+  FriendLabelService::delete(pool.get_ref(), label_id, claims.sub).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+async fn add_label_members(pool: web::Data<MySqlPool>, path: web::Path<i64>, body: web::Json<LabelMembersReq>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let label_id = path.into_inner();
+  FriendLabelService::add_members(pool.get_ref(), label_id, claims.sub, &body.friend_ids).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+async fn remove_label_member(pool: web::Data<MySqlPool>, path: web::Path<(i64, i64)>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let (label_id, friend_id) = path.into_inner();
+  FriendLabelService::remove_member(pool.get_ref(), label_id, claims.sub, friend_id).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
   let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
 
   cfg.service(
     web::scope("/friendships")
-    .wrap(Auth { jwt_secret })
+    .wrap(Auth { jwt_secret, scope: None })
     .route("", web::post().to(send_friendship_request))
     .route("", web::get().to(list_friends))
+    .route("/{id}", web::delete().to(cancel_friendship))
     .route("/{id}/accept", web::post().to(accept_friendship))
     .route("/{id}/reject", web::post().to(reject_friendship))
     .route("/pending", web::get().to(list_pending))
+    .route("/outgoing", web::get().to(list_outgoing))
+    .route("/suggestions", web::get().to(list_suggestions))
+    .route("/friends/{user_id}", web::delete().to(remove_friend))
+    .route("/labels", web::get().to(list_labels))
+    .route("/labels", web::post().to(create_label))
+    .route("/labels/{id}", web::put().to(rename_label))
+    .route("/labels/{id}", web::delete().to(delete_label))
+    .route("/labels/{id}/members", web::post().to(add_label_members))
+    .route("/labels/{id}/members/{friend_id}", web::delete().to(remove_label_member))
   );
 }
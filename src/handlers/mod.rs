@@ -1,15 +1,30 @@
 pub mod auth;
 pub mod note;
+pub mod folder;
+pub mod tag;
 pub mod ws;
 pub mod conversation;
 pub mod friendship;
 pub mod user;
+pub mod files;
+pub mod admin;
+pub mod public;
 
 pub use note::configure as note_configure;
+pub use folder::configure as folder_configure;
+pub use tag::configure as tag_configure;
 pub use auth::configure as auth_configure;
 pub use conversation::configure as conversation_configure;
 pub use friendship::configure as friendship_configure;
 pub use user::configure as user_configure;
+pub use files::configure as files_configure;
+pub use admin::configure as admin_configure;
+pub use public::configure as public_configure;
 
 pub use ws::ChatServer;
+pub use ws::BlockChanged;
+pub use ws::Notify;
+pub use ws::Kick;
+pub use ws::Broadcast;
+pub use ws::ProfileChanged;
 pub use ws::chat_route;
@@ -1,33 +1,94 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
 use sqlx::MySqlPool;
 use std::env;
+use validator::Validate;
+use crate::config::AppConfig;
 use crate::error::AppError;
-use crate::middleware::Auth;
-use crate::models::{CreateNote, UpdateNote};
-use crate::services::NoteService;
-use crate::utils::Claims;
+use crate::middleware::{Auth, ApiScope};
+use crate::models::{CreateNote, UpdateNote, ShareNote, MoveTarget, RenderedNote, NoteExportFormat, ImportFormat, PublishNote, NewAttachment, CreateTemplate, UpdateTemplate};
+use crate::services::{NoteService, FriendLabelService, ExportService, ImportService, PublicationService, AttachmentService, LinkService, TemplateService};
+use crate::utils::{Claims, Storage};
 
-async fn list(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
-    let notes = NoteService::find_all(pool.get_ref(), claims.sub).await?;
+/// 导入文件大小上限 (zip 解压后另有限制)
+const IMPORT_MAX_BYTES: usize = 20 * 1024 * 1024;
+/// 单个附件大小上限，总空间另见 ATTACHMENT_QUOTA_MB
+const ATTACHMENT_MAX_BYTES: usize = 20 * 1024 * 1024;
+
+/// `?tag=a&tag=b` 按标签筛选，`match=any` 为任意一个，默认 `all` 要求全部
+async fn list(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<Vec<(String, String)>>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let tags: Vec<String> = query.iter()
+        .filter(|(key, value)| key == "tag" && !value.trim().is_empty())
+        .map(|(_, value)| value.trim().to_string())
+        .collect();
+    let match_all = match query.iter().rev().find(|(key, _)| key == "match").map(|(_, value)| value.as_str()) {
+        None | Some("all") => true,
+        Some("any") => false,
+        Some(_) => return Err(AppError::BadRequest("match 只能是 all 或 any".to_string())),
+    };
+
+    let notes = if tags.is_empty() {
+        NoteService::find_all(pool.get_ref(), claims.sub).await?
+    } else {
+        NoteService::find_by_tags(pool.get_ref(), claims.sub, &tags, match_all).await?
+    };
     Ok(HttpResponse::Ok().json(notes))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GetNoteQuery {
+    /// 目前只支持 html
+    render: Option<String>,
+}
+
 async fn get_by_id(
     pool: web::Data<MySqlPool>,
     path: web::Path<i64>,
+    query: web::Query<GetNoteQuery>,
+    claims: web::ReqData<Claims>
 ) -> Result<HttpResponse, AppError> {
     let id = path.into_inner();
-    let note = NoteService::find_by_id(pool.get_ref(), id).await?;
-    Ok(HttpResponse::Ok().json(note))
+    let note = NoteService::find_accessible(pool.get_ref(), id, claims.sub).await?;
+    match query.render.as_deref() {
+        None => Ok(HttpResponse::Ok().json(note)),
+        Some("html") => {
+            let html = web::block(move || {
+                let html = note.render_html();
+                RenderedNote { note, html }
+            })
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            Ok(HttpResponse::Ok().json(html))
+        }
+        Some(_) => Err(AppError::BadRequest("render 只支持 html".to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateNoteQuery {
+    template_id: Option<i64>,
+    /// 模板占位符用的时区，相对 UTC 的分钟数 (-720 到 840)，默认 0
+    utc_offset: Option<i32>,
 }
 
+/// `?template_id=` 按模板创建，请求体里传了的字段优先
 async fn create(
     pool: web::Data<MySqlPool>,
+    query: web::Query<CreateNoteQuery>,
     body: web::Json<CreateNote>,
     claims: web::ReqData<Claims>
 ) -> Result<HttpResponse, AppError> {
     tracing::info!("Creating note");
-    let note = NoteService::create(pool.get_ref(), body.into_inner(), claims.sub).await?;
+    let mut data = body.into_inner();
+    if let Some(template_id) = query.template_id {
+        TemplateService::apply(pool.get_ref(), template_id, claims.sub, query.utc_offset.unwrap_or(0), &mut data).await?;
+    }
+    data.validate()?;
+    let note = NoteService::create(pool.get_ref(), data, claims.sub).await?;
     Ok(HttpResponse::Created().json(note))
 }
 
@@ -35,18 +96,331 @@ async fn update(
     pool: web::Data<MySqlPool>,
     path: web::Path<i64>,
     body: web::Json<UpdateNote>,
+    claims: web::ReqData<Claims>
 ) -> Result<HttpResponse, AppError> {
+    body.validate()?;
     let id = path.into_inner();
-    let note = NoteService::update(pool.get_ref(), id, body.into_inner()).await?;
+    let note = NoteService::update(pool.get_ref(), id, claims.sub, body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(note))
+}
+
+async fn move_to(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<MoveTarget>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let note = NoteService::move_to(pool.get_ref(), path.into_inner(), claims.sub, body.folder_id).await?;
     Ok(HttpResponse::Ok().json(note))
 }
 
 async fn delete(
     pool: web::Data<MySqlPool>,
     path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    NoteService::delete(pool.get_ref(), id, claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportNoteQuery {
+    format: NoteExportFormat,
+}
+
+/// 单篇导出为 md / html / pdf 文件
+async fn export_note(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    query: web::Query<ExportNoteQuery>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let note = NoteService::find_accessible(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    let format = query.format;
+    let (content_type, filename, data) = web::block(move || ExportService::export_note(&note, format))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(Storage::content_disposition(false, &filename))
+        .body(data))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportNotesQuery {
+    /// 为空时导出全部笔记
+    folder_id: Option<i64>,
+}
+
+/// 批量导出为 Markdown zip 包
+async fn export_notes(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<ExportNotesQuery>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let data = ExportService::export_notes(pool.get_ref(), claims.sub, query.folder_id).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(Storage::content_disposition(false, "notes.zip"))
+        .body(data))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    format: ImportFormat,
+    /// 导入到哪个文件夹，为空时是根目录
+    folder_id: Option<i64>,
+    /// 单个 Markdown 没有标题时用文件名
+    filename: Option<String>,
+}
+
+/// 请求体是原始文件内容
+async fn import(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    if body.is_empty() {
+        return Err(AppError::BadRequest("文件内容为空".to_string()));
+    }
+    let ImportQuery { format, folder_id, filename } = query.into_inner();
+    let notes = ImportService::import(pool.get_ref(), claims.sub, format, folder_id, filename, body).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "code": 201,
+        "imported": notes.len(),
+        "notes": notes
+    })))
+}
+
+async fn list_trash(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let notes = NoteService::find_trash(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(notes))
+}
+
+async fn empty_trash(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let deleted = NoteService::empty_trash(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 200,
+        "deleted": deleted
+    })))
+}
+
+async fn restore(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let note = NoteService::restore(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(note))
+}
+
+async fn delete_permanently(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
 ) -> Result<HttpResponse, AppError> {
+    NoteService::delete_permanently(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn list_shared(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let notes = NoteService::find_shared_with(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(notes))
+}
+
+async fn share(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<ShareNote>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
     let id = path.into_inner();
-    NoteService::delete(pool.get_ref(), id).await?;
+    let ShareNote { mut user_ids, label_ids } = body.into_inner();
+    user_ids.extend(FriendLabelService::resolve_members(pool.get_ref(), claims.sub, &label_ids).await?);
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    NoteService::share(pool.get_ref(), id, claims.sub, &user_ids).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn unshare(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i64, i64)>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let (id, user_id) = path.into_inner();
+    NoteService::unshare(pool.get_ref(), id, claims.sub, user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn list_published(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let publications = PublicationService::find_all(pool.get_ref(), &config.app_url, claims.sub).await?;
+    Ok(HttpResponse::Ok().json(publications))
+}
+
+async fn get_publication(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let publication = PublicationService::find_for_note(pool.get_ref(), &config.app_url, path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(publication))
+}
+
+/// 发布或修改密码、有效期，链接不变
+async fn publish(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    path: web::Path<i64>,
+    body: web::Json<PublishNote>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let publication = PublicationService::publish(pool.get_ref(), &config.app_url, path.into_inner(), claims.sub, body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(publication))
+}
+
+async fn unpublish(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    PublicationService::unpublish(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn list_attachments(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let attachments = AttachmentService::find_for_note(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadAttachmentQuery {
+    filename: String,
+}
+
+/// 请求体是文件原始内容，类型取 Content-Type 请求头；返回的 `markdown` 可以直接插入正文
+async fn upload_attachment(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    storage: web::Data<Storage>,
+    config: web::Data<AppConfig>,
+    path: web::Path<i64>,
+    body: web::Bytes,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let query = web::Query::<UploadAttachmentQuery>::from_query(req.query_string())
+        .map_err(|_| AppError::BadRequest("缺少 filename 参数".to_string()))?;
+    if body.is_empty() {
+        return Err(AppError::BadRequest("文件内容为空".to_string()));
+    }
+    let upload = NewAttachment {
+        filename: query.into_inner().filename,
+        content_type: req.headers().get("Content-Type").and_then(|h| h.to_str().ok()).unwrap_or("").to_string(),
+        data: body.to_vec(),
+    };
+    let attachment = AttachmentService::upload(
+        pool.get_ref(), storage.get_ref(), config.attachment_quota_bytes, path.into_inner(), claims.sub, upload
+    ).await?;
+    Ok(HttpResponse::Created().json(attachment))
+}
+
+async fn delete_attachment(
+    pool: web::Data<MySqlPool>,
+    storage: web::Data<Storage>,
+    path: web::Path<(i64, i64)>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let (id, attachment_id) = path.into_inner();
+    AttachmentService::delete(pool.get_ref(), storage.get_ref(), id, attachment_id, claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn attachment_usage(
+    pool: web::Data<MySqlPool>,
+    config: web::Data<AppConfig>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let usage = AttachmentService::usage(pool.get_ref(), claims.sub, config.attachment_quota_bytes).await?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// 出链，`target_id` 为空的是断链
+async fn list_links(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let note = NoteService::find_accessible(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    let links = LinkService::outgoing(pool.get_ref(), note.id).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+async fn list_backlinks(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let note = NoteService::find_accessible(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    let notes = LinkService::backlinks(pool.get_ref(), note.id, claims.sub).await?;
+    Ok(HttpResponse::Ok().json(notes))
+}
+
+async fn list_broken_links(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let links = LinkService::broken(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+async fn graph(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let graph = LinkService::graph(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(graph))
+}
+
+/// 自己的模板和全局模板 (内置的和管理员维护的)
+async fn list_templates(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let templates = TemplateService::find_all(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+async fn create_template(
+    pool: web::Data<MySqlPool>,
+    body: web::Json<CreateTemplate>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let template = TemplateService::create(pool.get_ref(), Some(claims.sub), body.into_inner()).await?;
+    Ok(HttpResponse::Created().json(template))
+}
+
+async fn update_template(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<UpdateTemplate>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let template = TemplateService::update(pool.get_ref(), path.into_inner(), Some(claims.sub), body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(template))
+}
+
+async fn delete_template(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    TemplateService::delete(pool.get_ref(), path.into_inner(), Some(claims.sub)).await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -55,11 +429,46 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
 
     cfg.service(
         web::scope("/notes")
-            .wrap(Auth { jwt_secret })
+            .wrap(Auth { jwt_secret, scope: Some(ApiScope::NOTES) })
             .route("", web::get().to(list))
             .route("", web::post().to(create))
+            .route("/shared", web::get().to(list_shared))
+            .route("/published", web::get().to(list_published))
+            .route("/attachments/usage", web::get().to(attachment_usage))
+            .route("/links/broken", web::get().to(list_broken_links))
+            .route("/graph", web::get().to(graph))
+            .route("/templates", web::get().to(list_templates))
+            .route("/templates", web::post().to(create_template))
+            .route("/templates/{id}", web::put().to(update_template))
+            .route("/templates/{id}", web::delete().to(delete_template))
+            .route("/export", web::get().to(export_notes))
+            .service(
+                web::resource("/import")
+                    .app_data(web::PayloadConfig::new(IMPORT_MAX_BYTES))
+                    .route(web::post().to(import))
+            )
+            .route("/trash", web::get().to(list_trash))
+            .route("/trash", web::delete().to(empty_trash))
+            .route("/trash/{id}", web::delete().to(delete_permanently))
             .route("/{id}", web::get().to(get_by_id))
             .route("/{id}", web::put().to(update))
             .route("/{id}", web::delete().to(delete))
+            .route("/{id}/export", web::get().to(export_note))
+            .route("/{id}/move", web::post().to(move_to))
+            .route("/{id}/restore", web::post().to(restore))
+            .route("/{id}/publication", web::get().to(get_publication))
+            .route("/{id}/publication", web::put().to(publish))
+            .route("/{id}/publication", web::delete().to(unpublish))
+            .service(
+                web::resource("/{id}/attachments")
+                    .app_data(web::PayloadConfig::new(ATTACHMENT_MAX_BYTES))
+                    .route(web::get().to(list_attachments))
+                    .route(web::post().to(upload_attachment))
+            )
+            .route("/{id}/attachments/{attachment_id}", web::delete().to(delete_attachment))
+            .route("/{id}/links", web::get().to(list_links))
+            .route("/{id}/backlinks", web::get().to(list_backlinks))
+            .route("/{id}/shares", web::post().to(share))
+            .route("/{id}/shares/{user_id}", web::delete().to(unshare))
     );
 }
\ No newline at end of file
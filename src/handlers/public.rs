@@ -0,0 +1,34 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::MySqlPool;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::services::PublicationService;
+
+/// 有密码的公开笔记通过这个请求头传密码，不放在 URL 里以免进访问日志
+const PASSWORD_HEADER: &str = "X-Note-Password";
+
+/// 公开笔记，不需要登录
+async fn get_note(
+  req: HttpRequest,
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+  let password = req.headers().get(PASSWORD_HEADER).and_then(|value| value.to_str().ok());
+  // 用对端地址而不是 X-Forwarded-For，避免伪造 IP 绕过限流
+  let ip = req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string());
+  let note = PublicationService::view(pool.get_ref(), &config.login_throttle, &path.into_inner(), password, &ip).await?;
+
+  Ok(HttpResponse::Ok()
+    .insert_header(("Cache-Control", "no-store"))
+    .insert_header(("X-Robots-Tag", "noindex"))
+    .json(note))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+  cfg.service(
+    web::scope("/public")
+    .route("/notes/{token}", web::get().to(get_note))
+  );
+}
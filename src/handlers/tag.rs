@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use std::env;
+use validator::Validate;
+use crate::error::AppError;
+use crate::middleware::{Auth, ApiScope};
+use crate::models::{RenameTag, MergeTags};
+use crate::services::TagService;
+use crate::utils::Claims;
+
+#[derive(Debug, Deserialize)]
+pub struct AutocompleteQuery {
+    q: String,
+    limit: Option<i64>,
+}
+
+async fn list(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+    let tags = TagService::find_all(pool.get_ref(), claims.sub).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+async fn autocomplete(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<AutocompleteQuery>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let tags = TagService::autocomplete(pool.get_ref(), claims.sub, query.q.trim(), limit).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+async fn rename(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    body: web::Json<RenameTag>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let tag = TagService::rename(pool.get_ref(), path.into_inner(), claims.sub, &body.name).await?;
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+async fn merge(
+    pool: web::Data<MySqlPool>,
+    body: web::Json<MergeTags>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let tag = TagService::merge(pool.get_ref(), claims.sub, &body.source_ids, body.target_id).await?;
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+async fn delete(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<i64>,
+    claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+    TagService::delete(pool.get_ref(), path.into_inner(), claims.sub).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
+
+    cfg.service(
+        web::scope("/tags")
+            .wrap(Auth { jwt_secret, scope: Some(ApiScope::NOTES) })
+            .route("", web::get().to(list))
+            .route("/autocomplete", web::get().to(autocomplete))
+            .route("/merge", web::post().to(merge))
+            .route("/{id}", web::put().to(rename))
+            .route("/{id}", web::delete().to(delete))
+    );
+}
@@ -1,39 +1,337 @@
 use std::env;
 
-use actix_web::{HttpResponse, web::{self, ServiceConfig}};
+use actix::Addr;
+use actix_web::{HttpRequest, HttpResponse, web::{self, ServiceConfig}};
 use sqlx::MySqlPool;
+use validator::Validate;
 
-use crate::{error::AppError, middleware::Auth, services::UserService};
+use crate::{error::AppError, middleware::Auth, models::{ChangePassword, CreateApiToken, DeleteAccount, DisableTwoFactor, TwoFactorCode, UpdatePrivacy, UpdateProfile, UserProfile}, services::{AccountDeletionService, AccountService, ApiTokenService, ExportService, BlockService, SsoService, TwoFactorService, UserService}, utils::{Claims, JwtUtil, Mailer, OidcClient, Storage}};
+use crate::config::AppConfig;
+use crate::handlers::{BlockChanged, ChatServer, Kick, ProfileChanged};
+use crate::handlers::auth::{oidc_client, OidcCallbackRequest};
+
+/// 头像大小上限 2MB
+const AVATAR_MAX_BYTES: usize = 2 * 1024 * 1024;
 
 #[derive(serde::Deserialize)]
 struct SearchQuery {
     q: String,
+    limit: Option<i64>,
 }
 
 #[allow(private_interfaces)]
-pub async fn search_user(pool: web::Data<MySqlPool>, query: web::Query<SearchQuery>) -> Result<HttpResponse, AppError> {
-  let q = query.into_inner().q;
-  let result = UserService::search(pool.get_ref(), &q).await?;
+pub async fn search_user(pool: web::Data<MySqlPool>, query: web::Query<SearchQuery>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let SearchQuery { q, limit } = query.into_inner();
+  let limit = limit.unwrap_or(20).clamp(1, 50);
+  let result = UserService::search(pool.get_ref(), claims.sub, &q, limit).await?;
   Ok(HttpResponse::Ok().json(result))
 }
 
 pub async fn get_user(
   pool: web::Data<MySqlPool>,
-  path: web::Path<i64>
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
 ) -> Result<HttpResponse, AppError> {
   let id = path.into_inner();
-  let user = UserService::find_by_id(pool.get_ref(), id).await?;
+  let user = UserService::find_public(pool.get_ref(), claims.sub, id).await?;
+
+  Ok(HttpResponse::Ok().json(user))
+}
 
+pub async fn get_me(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let user = UserService::find_by_id(pool.get_ref(), claims.sub).await?;
   Ok(HttpResponse::Ok().json(user))
 }
 
+pub async fn update_me(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  body: web::Json<UpdateProfile>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let user = UserService::update_profile(pool.get_ref(), claims.sub, body.into_inner()).await?;
+  let response = HttpResponse::Ok().json(&user);
+  server.do_send(ProfileChanged { profile: UserProfile::from(user) });
+
+  Ok(response)
+}
+
+pub async fn update_privacy(pool: web::Data<MySqlPool>, body: web::Json<UpdatePrivacy>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let user = UserService::update_privacy(pool.get_ref(), claims.sub, body.into_inner()).await?;
+  Ok(HttpResponse::Ok().json(user))
+}
+
+/// 改密码后其他会话全部失效并断开 WebSocket，当前会话用返回的新 token 重新连接
+pub async fn change_password(
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  server: web::Data<Addr<ChatServer>>,
+  body: web::Json<ChangePassword>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  AppError::validate(body.validate(), config.password_policy.check("new_password", &body.new_password))?;
+  let ChangePassword { current_password, new_password } = body.into_inner();
+  let token_version = AccountService::change_password(pool.get_ref(), claims.sub, &current_password, &new_password).await?;
+  let token = JwtUtil::generate_token(claims.sub, token_version, &config.jwt_secret)?;
+  server.do_send(Kick { user_id: claims.sub, reason: "密码已修改，请重新登录".to_string() });
+
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "token": token
+  })))
+}
+
+pub async fn resend_email_verification(
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  mailer: web::Data<dyn Mailer>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let user = UserService::find_by_id(pool.get_ref(), claims.sub).await?;
+  AccountService::send_email_verification(pool.get_ref(), mailer.get_ref(), &config.app_url, &user).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+/// 个人数据导出，zip 包直接作为附件下载
+pub async fn export_me(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let archive = ExportService::export_user(pool.get_ref(), claims.sub).await?;
+  let filename = format!("export-{}-{}.zip", claims.sub, chrono::Utc::now().format("%Y%m%d"));
+
+  Ok(HttpResponse::Ok()
+    .content_type("application/zip")
+    .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+    .body(archive))
+}
+
+/// 申请注销：所有 token 失效并断开 WebSocket，宽限期内重新登录可以撤销
+pub async fn delete_me(
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  server: web::Data<Addr<ChatServer>>,
+  body: web::Json<DeleteAccount>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let password = match (config.password_login, body.password.as_deref()) {
+    (true, None) => return Err(AppError::BadRequest("请输入密码确认注销".to_string())),
+    (true, password) => password,
+    (false, _) => None,
+  };
+  let scheduled_at = AccountDeletionService::request(pool.get_ref(), claims.sub, password, config.account_deletion_grace_days).await?;
+  server.do_send(Kick { user_id: claims.sub, reason: "账号已申请注销".to_string() });
+
+  Ok(HttpResponse::Accepted().json(serde_json::json!({
+    "code": 202,
+    "deletion_scheduled_at": scheduled_at
+  })))
+}
+
+pub async fn cancel_deletion(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  AccountDeletionService::cancel(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+/// 开始绑定身份验证器，返回密钥和 otpauth 链接
+pub async fn setup_two_factor(pool: web::Data<MySqlPool>, config: web::Data<AppConfig>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let user = UserService::find_by_id(pool.get_ref(), claims.sub).await?;
+  let setup = TwoFactorService::begin_enrollment(pool.get_ref(), &user, &config.totp_issuer).await?;
+  Ok(HttpResponse::Ok().json(setup))
+}
+
+pub async fn confirm_two_factor(pool: web::Data<MySqlPool>, body: web::Json<TwoFactorCode>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let recovery_codes = TwoFactorService::confirm_enrollment(pool.get_ref(), claims.sub, &body.code).await?;
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "recovery_codes": recovery_codes
+  })))
+}
+
+pub async fn disable_two_factor(
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  body: web::Json<DisableTwoFactor>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  TwoFactorService::disable(pool.get_ref(), claims.sub, &body.password, &body.code, config.require_two_factor).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn regenerate_recovery_codes(pool: web::Data<MySqlPool>, body: web::Json<TwoFactorCode>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let recovery_codes = TwoFactorService::regenerate_recovery_codes(pool.get_ref(), claims.sub, &body.code).await?;
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "recovery_codes": recovery_codes
+  })))
+}
+
+pub async fn list_api_tokens(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let result = ApiTokenService::list(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// 明文令牌只在这里返回一次
+pub async fn create_api_token(pool: web::Data<MySqlPool>, body: web::Json<CreateApiToken>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let (api_token, token) = ApiTokenService::create(pool.get_ref(), claims.sub, body.into_inner()).await?;
+  Ok(HttpResponse::Created().json(serde_json::json!({
+    "code": 201,
+    "token": token,
+    "data": api_token
+  })))
+}
+
+pub async fn revoke_api_token(pool: web::Data<MySqlPool>, path: web::Path<i64>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  ApiTokenService::revoke(pool.get_ref(), path.into_inner(), claims.sub).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_identities(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let result = SsoService::list_identities(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// 已登录用户关联 SSO 身份，返回授权地址；IdP 回调后前端带着登录状态调用 /users/me/sso/link/callback
+pub async fn link_identity(
+  pool: web::Data<MySqlPool>,
+  client: Option<web::Data<OidcClient>>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let client = oidc_client(client)?;
+  let authorization_url = SsoService::begin(pool.get_ref(), &client, Some(claims.sub)).await?;
+  Ok(HttpResponse::Ok().json(serde_json::json!({
+    "code": 200,
+    "authorization_url": authorization_url
+  })))
+}
+
+/// 只接受当前用户自己发起的关联请求，不签发新 token
+pub async fn complete_link_identity(
+  pool: web::Data<MySqlPool>,
+  client: Option<web::Data<OidcClient>>,
+  body: web::Json<OidcCallbackRequest>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  body.validate()?;
+  let client = oidc_client(client)?;
+  SsoService::complete_link(pool.get_ref(), &client, &body.code, &body.state, claims.sub).await?;
+  let result = SsoService::list_identities(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// 关闭密码登录时必须保留 SSO 身份，否则无法再登录
+pub async fn unlink_identity(
+  pool: web::Data<MySqlPool>,
+  config: web::Data<AppConfig>,
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  if !config.password_login {
+    return Err(AppError::Forbidden("只允许单点登录时不能取消关联".to_string()));
+  }
+  SsoService::unlink(pool.get_ref(), claims.sub, path.into_inner()).await?;
+  Ok(HttpResponse::NoContent().finish())
+}
+
+/// 请求体就是图片本身，格式由 Content-Type 决定
+pub async fn upload_avatar(
+  req: HttpRequest,
+  pool: web::Data<MySqlPool>,
+  storage: web::Data<Storage>,
+  server: web::Data<Addr<ChatServer>>,
+  body: web::Bytes,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let content_type = req.headers().get("Content-Type").and_then(|h| h.to_str().ok()).unwrap_or("");
+  let ext = Storage::image_extension(content_type)
+    .ok_or_else(|| AppError::BadRequest("头像只支持 png / jpeg / gif / webp".to_string()))?;
+  if body.is_empty() {
+    return Err(AppError::BadRequest("头像不能为空".to_string()));
+  }
+
+  let old = UserService::find_by_id(pool.get_ref(), claims.sub).await?;
+  let key = format!("avatars/{}-{}.{}", claims.sub, chrono::Utc::now().timestamp_millis(), ext);
+  storage.save(&key, body.to_vec()).await?;
+
+  let user = UserService::update_avatar(pool.get_ref(), claims.sub, &format!("/files/{}", key)).await?;
+  if let Some(old_key) = old.avatar_url.as_deref().and_then(|url| url.strip_prefix("/files/")) {
+    storage.delete(old_key).await?;
+  }
+  let response = HttpResponse::Ok().json(&user);
+  server.do_send(ProfileChanged { profile: UserProfile::from(user) });
+
+  Ok(response)
+}
+
+pub async fn block_user(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let blocked_id = path.into_inner();
+  UserService::find_by_id(pool.get_ref(), blocked_id).await?;
+  BlockService::block(pool.get_ref(), claims.sub, blocked_id).await?;
+  server.do_send(BlockChanged { blocker_id: claims.sub, blocked_id, blocked: true });
+
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn unblock_user(
+  pool: web::Data<MySqlPool>,
+  server: web::Data<Addr<ChatServer>>,
+  path: web::Path<i64>,
+  claims: web::ReqData<Claims>
+) -> Result<HttpResponse, AppError> {
+  let blocked_id = path.into_inner();
+  BlockService::unblock(pool.get_ref(), claims.sub, blocked_id).await?;
+  server.do_send(BlockChanged { blocker_id: claims.sub, blocked_id, blocked: false });
+
+  Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_blocked(pool: web::Data<MySqlPool>, claims: web::ReqData<Claims>) -> Result<HttpResponse, AppError> {
+  let result = BlockService::list_blocked(pool.get_ref(), claims.sub).await?;
+  Ok(HttpResponse::Ok().json(result))
+}
+
 pub fn configure(cfg: &mut ServiceConfig) {
   let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET is required");
 
   cfg.service(
     web::scope("/users")
-    .wrap(Auth { jwt_secret })
+    .wrap(Auth { jwt_secret, scope: None })
+    .route("/me", web::get().to(get_me))
+    .route("/me", web::patch().to(update_me))
+    .route("/me", web::delete().to(delete_me))
+    .route("/me/deletion", web::delete().to(cancel_deletion))
+    .route("/me/export", web::get().to(export_me))
+    .route("/me/privacy", web::put().to(update_privacy))
+    .route("/me/password", web::post().to(change_password))
+    .route("/me/email/verification", web::post().to(resend_email_verification))
+    .route("/me/tokens", web::get().to(list_api_tokens))
+    .route("/me/tokens", web::post().to(create_api_token))
+    .route("/me/tokens/{id}", web::delete().to(revoke_api_token))
+    .route("/me/sso", web::get().to(list_identities))
+    .route("/me/sso/link", web::post().to(link_identity))
+    .route("/me/sso/link/callback", web::post().to(complete_link_identity))
+    .route("/me/sso/{id}", web::delete().to(unlink_identity))
+    .route("/me/2fa/setup", web::post().to(setup_two_factor))
+    .route("/me/2fa/confirm", web::post().to(confirm_two_factor))
+    .route("/me/2fa/disable", web::post().to(disable_two_factor))
+    .route("/me/2fa/recovery-codes", web::post().to(regenerate_recovery_codes))
+    .service(
+      web::resource("/me/avatar")
+        .app_data(web::PayloadConfig::new(AVATAR_MAX_BYTES))
+        .route(web::put().to(upload_avatar))
+    )
     .route("/search", web::get().to(search_user))
+    .route("/blocked", web::get().to(list_blocked))
     .route("/{id}", web::get().to(get_user))
+    .route("/{id}/block", web::post().to(block_user))
+    .route("/{id}/block", web::delete().to(unblock_user))
   );
 }
\ No newline at end of file
@@ -12,10 +12,15 @@ use crate::services::MessageRepository;
 use crate::utils::JwtUtil;
 use crate::config::AppConfig;
 use crate::services::UserService;
+use crate::services::ConversationServices;
+use crate::services::BlockService;
+use crate::services::{ApiTokenService, API_TOKEN_PREFIX};
+use crate::models::UserProfile;
 
 const HEARTBEAT_INTERVAL:Duration = Duration::from_secs(30);
 const CLIENT_TIMEOUT:Duration = Duration::from_secs(60);
 
+/// 由 WsSession 在确认发送方是会话成员后发出
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct ClientMessage {
@@ -38,11 +43,21 @@ enum ClientAction {
     Msg { conversation_id: i64, msg: String }
 }
 
+/// 服务端主动关闭连接 (强制下线、禁用账号)
+#[derive(Message)]
+#[rtype(result = "()")]
+struct CloseSession {
+    reason: String
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
 struct Connect {
     user_id: i64,
+    profile: UserProfile,
+    blocked: HashSet<i64>,
     addr: Recipient<ServerMessage>,
+    closer: Recipient<CloseSession>,
 }
 
 #[derive(Message)]
@@ -51,6 +66,7 @@ struct Disconnect {
     user_id: i64,
 }
 
+/// 由 WsSession 在确认是会话成员后发出，ChatServer 不再查库
 #[derive(Message)]
 #[rtype(result = "()")]
 struct Join {
@@ -58,6 +74,48 @@ struct Join {
     conversation_id: i64
 }
 
+/// 推送给单个用户的通知 (好友请求等)，用户不在线则丢弃
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Notify {
+    pub user_id: i64,
+    pub msg: String
+}
+
+/// 用户修改资料后通知 ChatServer，之后的聊天消息带上新资料
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ProfileChanged {
+    pub profile: UserProfile
+}
+
+/// 断开用户的 WebSocket 连接，用户不在线则忽略
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Kick {
+    pub user_id: i64,
+    pub reason: String
+}
+
+/// HTTP 接口产生的会话消息 (笔记卡片等)，推送给房间内的在线成员，
+/// 和 ClientMessage 一样跳过拉黑了发送方的人；消息已由调用方保存
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Broadcast {
+    pub conversation_id: i64,
+    pub sender_id: i64,
+    pub msg: String
+}
+
+/// HTTP 拉黑/取消拉黑后通知 ChatServer 更新内存中的拉黑表
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BlockChanged {
+    pub blocker_id: i64,
+    pub blocked_id: i64,
+    pub blocked: bool
+}
+
 pub struct ChatServer {
     /**
      * This i64 is for user_id  
@@ -68,6 +126,18 @@ pub struct ChatServer {
      * HashSet<i64> The second i64 is for user_id
      */
     rooms: HashMap<i64, HashSet<i64>>,  
+    /**
+     * 在线用户的拉黑表：user_id -> 该用户拉黑的 user_id 集合
+     */
+    blocks: HashMap<i64, HashSet<i64>>,
+    /**
+     * 在线用户的资料，广播消息时嵌入发送方资料
+     */
+    profiles: HashMap<i64, UserProfile>,
+    /**
+     * 在线用户的连接，用于服务端主动断开
+     */
+    closers: HashMap<i64, Recipient<CloseSession>>,
     pool: MySqlPool
 }
 impl ChatServer {
@@ -75,9 +145,16 @@ impl ChatServer {
         ChatServer { 
             sessions: HashMap::new(),
             pool,
-            rooms: HashMap::new()
+            rooms: HashMap::new(),
+            blocks: HashMap::new(),
+            profiles: HashMap::new(),
+            closers: HashMap::new()
         }
     }
+
+    fn has_blocked(&self, user_id: i64, other_id: i64) -> bool {
+        self.blocks.get(&user_id).is_some_and(|set| set.contains(&other_id))
+    }
 }
 impl Actor for ChatServer {
     type Context = Context<Self>;
@@ -87,14 +164,42 @@ impl Actor for ChatServer {
 struct WsSession {
     // TODO(human): 把 id: usize 替换为真实用户身份字段，并修改 new() 的参数和构造
     user_id: i64,
-    user_name: String,                                                
+    profile: UserProfile,
+    /**
+     * 握手时加载的拉黑表，Connect 时交给 ChatServer
+     */
+    blocked: HashSet<i64>,
     server: Addr<ChatServer>,
     pool: MySqlPool,
     hb: Instant
 }
 impl WsSession {
-    pub fn new (server: Addr<ChatServer>, user_id: i64, user_name: String, pool: MySqlPool) -> Self {
-        WsSession { user_id, user_name, server, pool, hb: Instant::now() }
+    pub fn new (server: Addr<ChatServer>, profile: UserProfile, blocked: HashSet<i64>, pool: MySqlPool) -> Self {
+        WsSession { user_id: profile.id, profile, blocked, server, pool, hb: Instant::now() }
+    }
+
+    /// 确认是会话成员后再执行 `then`；用 ctx.wait 按顺序处理，同一连接的消息不会乱序
+    fn if_member<F>(&mut self, ctx: &mut ws::WebsocketContext<Self>, conversation_id: i64, then: F)
+    where
+        F: FnOnce(&mut Self) + 'static
+    {
+        let pool = self.pool.clone();
+        let user_id = self.user_id;
+        ctx.wait(
+            async move { ConversationServices::is_member(&pool, conversation_id, user_id).await }
+                .into_actor(self)
+                .map(move |result, act, ctx| {
+                    match result {
+                        Ok(true) => then(act),
+                        Ok(false) => ctx.text(serde_json::json!({
+                            "type": "error",
+                            "conversation_id": conversation_id,
+                            "message": "不是该会话的成员"
+                        }).to_string()),
+                        Err(e) => tracing::error!("会话成员校验失败：{}", e),
+                    }
+                })
+        );
     }
 }
 impl Actor for WsSession {
@@ -106,7 +211,10 @@ impl Actor for WsSession {
 
         self.server.do_send(Connect {
             user_id: self.user_id,
-            addr: addr.recipient(),
+            profile: self.profile.clone(),
+            blocked: std::mem::take(&mut self.blocked),
+            addr: addr.clone().recipient(),
+            closer: addr.recipient(),
         });
 
         // 在这里启动心跳定时器
@@ -141,16 +249,20 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                 if let Ok(action) = serde_json::from_str::<ClientAction>(&text) {
                     match action {
                         ClientAction::Join { conversation_id } => {
-                            self.server.do_send(Join {
-                                user_id: self.user_id,
-                                conversation_id
+                            self.if_member(ctx, conversation_id, move |act| {
+                                act.server.do_send(Join {
+                                    user_id: act.user_id,
+                                    conversation_id
+                                });
                             });
                         },
                         ClientAction::Msg { conversation_id, msg } => {
-                            self.server.do_send(ClientMessage {
-                                user_id: self.user_id,
-                                msg,
-                                conversation_id
+                            self.if_member(ctx, conversation_id, move |act| {
+                                act.server.do_send(ClientMessage {
+                                    user_id: act.user_id,
+                                    msg,
+                                    conversation_id
+                                });
                             });
                         }
                     }
@@ -175,14 +287,35 @@ impl Handler<ServerMessage> for WsSession {
     }
 }
 
+impl Handler<CloseSession> for WsSession {
+    type Result = ();
+    fn handle(&mut self, msg: CloseSession, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some(msg.reason),
+        }));
+        ctx.stop();
+    }
+}
+
 impl Handler<ClientMessage> for ChatServer {
     type Result = ();
 
     fn handle(&mut self, msg: ClientMessage, _ctx: &mut Self::Context) -> Self::Result {
-        let broadcast_msg = format!("用户{}: {}", msg.user_id, msg.msg);
+        let broadcast_msg = serde_json::json!({
+            "type": "message",
+            "conversation_id": msg.conversation_id,
+            "sender": self.profiles.get(&msg.user_id),
+            "content": msg.msg,
+            "created_at": chrono::Utc::now()
+        }).to_string();
         
         if let Some(room_members) = self.rooms.get(&msg.conversation_id) {
             for user_id in room_members {
+                // 接收方拉黑了发送方，不推送
+                if self.has_blocked(*user_id, msg.user_id) {
+                    continue;
+                }
                 if let Some(recipient) = self.sessions.get(user_id) {
                     recipient.do_send(ServerMessage { msg: broadcast_msg.clone() });
                 }
@@ -192,11 +325,14 @@ impl Handler<ClientMessage> for ChatServer {
         let pool = self.pool.clone();
         actix::spawn(async move {
            if let Err(e) =  MessageRepository::save(&pool, msg.user_id, msg.conversation_id, &msg.msg).await {
-            eprintln!("消息保存失败：{}", e);
+            tracing::error!("消息保存失败：{}", e);
+           }
+           if let Err(e) = ConversationServices::on_new_message(&pool, msg.conversation_id).await {
+            tracing::error!("会话状态更新失败：{}", e);
            }
         });
 
-        println!("广播消息: {}", broadcast_msg)
+        tracing::debug!("广播消息: {}", broadcast_msg)
     }
 }
 
@@ -204,10 +340,12 @@ impl Handler<Connect> for ChatServer {
     type Result = ();
 
     fn handle(&mut self, msg: Connect, _ctx: &mut Self::Context) -> Self::Result {
-
+        // 拉黑表在握手时已经加载好，上线后的第一条消息就能过滤
         self.sessions.insert(msg.user_id, msg.addr);
-        println!("用户 {} 已连接，当前在线: {}", msg.user_id, self.sessions.len());
-
+        self.profiles.insert(msg.user_id, msg.profile);
+        self.blocks.insert(msg.user_id, msg.blocked);
+        self.closers.insert(msg.user_id, msg.closer);
+        tracing::info!("用户 {} 已连接，当前在线: {}", msg.user_id, self.sessions.len());
     }
 }
 
@@ -216,10 +354,13 @@ impl Handler<Disconnect> for ChatServer {
 
     fn handle(&mut self, msg: Disconnect, _ctx: &mut Self::Context) -> Self::Result {
         self.sessions.remove(&msg.user_id);
+        self.blocks.remove(&msg.user_id);
+        self.profiles.remove(&msg.user_id);
+        self.closers.remove(&msg.user_id);
         for (_key, room) in &mut self.rooms {
             room.remove(&msg.user_id);
         }
-        println!("用户 {} 已断开，当前在线: {}", msg.user_id, self.sessions.len());
+        tracing::info!("用户 {} 已断开，当前在线: {}", msg.user_id, self.sessions.len());
     }
 }
 
@@ -235,12 +376,12 @@ impl Handler<Join> for ChatServer {
         let recipient = self.sessions.get(&msg.user_id).cloned();
 
         actix::spawn(async move {
-           if let Ok(messages) =  MessageRepository::get_recent(&pool, msg.conversation_id, 20).await {
-                if let Some(recipient) = recipient {
-                    for message in messages {
-                        if let Ok(m) = serde_json::to_string(&message) {
-                            recipient.do_send(ServerMessage { msg: m });
-                        }
+            if let Ok(messages) = MessageRepository::get_recent(&pool, msg.conversation_id, msg.user_id, 20).await
+                && let Some(recipient) = recipient
+            {
+                for message in messages {
+                    if let Ok(m) = serde_json::to_string(&message) {
+                        recipient.do_send(ServerMessage { msg: m });
                     }
                 }
             }
@@ -248,6 +389,81 @@ impl Handler<Join> for ChatServer {
     }
 }
 
+impl Handler<Notify> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Notify, _ctx: &mut Self::Context) -> Self::Result {
+        if let Some(recipient) = self.sessions.get(&msg.user_id) {
+            recipient.do_send(ServerMessage { msg: msg.msg });
+        }
+    }
+}
+
+impl Handler<ProfileChanged> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: ProfileChanged, _ctx: &mut Self::Context) -> Self::Result {
+        if let Some(profile) = self.profiles.get_mut(&msg.profile.id) {
+            *profile = msg.profile;
+        }
+    }
+}
+
+impl Handler<Broadcast> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Broadcast, _ctx: &mut Self::Context) -> Self::Result {
+        if let Some(room_members) = self.rooms.get(&msg.conversation_id) {
+            for user_id in room_members {
+                if self.has_blocked(*user_id, msg.sender_id) {
+                    continue;
+                }
+                if let Some(recipient) = self.sessions.get(user_id) {
+                    recipient.do_send(ServerMessage { msg: msg.msg.clone() });
+                }
+            }
+        }
+    }
+}
+
+impl Handler<Kick> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Kick, _ctx: &mut Self::Context) -> Self::Result {
+        if let Some(closer) = self.closers.get(&msg.user_id) {
+            closer.do_send(CloseSession { reason: msg.reason });
+        }
+    }
+}
+
+impl Handler<BlockChanged> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BlockChanged, _ctx: &mut Self::Context) -> Self::Result {
+        // 只维护在线用户的拉黑表，离线用户下次 Connect 时重新加载
+        if let Some(set) = self.blocks.get_mut(&msg.blocker_id) {
+            if msg.blocked {
+                set.insert(msg.blocked_id);
+            } else {
+                set.remove(&msg.blocked_id);
+            }
+        }
+    }
+}
+
+/// 拉黑表加载失败就拒绝连接，不能在过滤不了的情况下放进来
+async fn start_session(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: &Addr<ChatServer>,
+    pool: &MySqlPool,
+    profile: UserProfile
+) -> Result<HttpResponse, Error> {
+    let blocked = BlockService::blocked_ids(pool, profile.id).await?.into_iter().collect();
+    let session = WsSession::new(server.clone(), profile, blocked, pool.clone());
+    ws::start(session, &req, stream)
+}
+
 #[derive(Deserialize)]
 pub struct Token {
     token: String
@@ -262,13 +478,36 @@ pub async fn chat_route(
     config: web::Data<AppConfig>,
     pool: web::Data<MySqlPool>
 ) -> Result<HttpResponse, Error> {
+    // 个人访问令牌需要 chat:read 和 chat:send 权限，连接上既收历史和新消息也能发消息
+    if token.token.starts_with(API_TOKEN_PREFIX) {
+        let found = ApiTokenService::authenticate(pool.get_ref(), &token.token).await?;
+        let Some((user_id, scopes)) = found else {
+            return Err(actix_web::error::ErrorUnauthorized("token 无效"));
+        };
+        for scope in ["chat:read", "chat:send"] {
+            if !scopes.iter().any(|s| s == scope) {
+                return Err(actix_web::error::ErrorForbidden(format!("token 缺少 {} 权限", scope)));
+            }
+        }
+        let user = UserService::find_by_id(pool.get_ref(), user_id).await.map_err(|_| actix_web::error::ErrorUnauthorized("用户不存在"))?;
+        if user.disabled_at.is_some() {
+            return Err(actix_web::error::ErrorForbidden("账号已被禁用"));
+        }
+        return start_session(req, stream, server.get_ref(), pool.get_ref(), UserProfile::from(user)).await;
+    }
+
     let token_handled = JwtUtil::verify_token(&token.token, &config.jwt_secret);
 
     match token_handled {
         Ok(claims) => {
             let user = UserService::find_by_id(pool.get_ref(), claims.sub).await.map_err(|_| actix_web::error::ErrorUnauthorized("用户不存在"))?;
-            let session = WsSession::new(server.get_ref().clone(), claims.sub, user.username, pool.get_ref().clone());
-            ws::start(session, &req, stream)
+            if user.token_version != claims.ver {
+                return Err(actix_web::error::ErrorUnauthorized("token 已失效"));
+            }
+            if user.disabled_at.is_some() {
+                return Err(actix_web::error::ErrorForbidden("账号已被禁用"));
+            }
+            start_session(req, stream, server.get_ref(), pool.get_ref(), UserProfile::from(user)).await
         },
         Err(_e) => {
             Ok(HttpResponse::Unauthorized().json(serde_json::json!({
@@ -278,4 +517,69 @@ pub async fn chat_route(
         }
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use actix::prelude::*;
+    use sqlx::mysql::MySqlPoolOptions;
+    use crate::models::UserProfile;
+    use super::{Broadcast, ChatServer, CloseSession, Connect, ServerMessage};
+
+    /// 代替 WsSession，记录收到的推送
+    struct Collector {
+        received: Arc<Mutex<Vec<String>>>
+    }
+    impl Actor for Collector {
+        type Context = Context<Self>;
+    }
+    impl Handler<ServerMessage> for Collector {
+        type Result = ();
+        fn handle(&mut self, msg: ServerMessage, _ctx: &mut Self::Context) {
+            self.received.lock().unwrap().push(msg.msg);
+        }
+    }
+    impl Handler<CloseSession> for Collector {
+        type Result = ();
+        fn handle(&mut self, _msg: CloseSession, _ctx: &mut Self::Context) {}
+    }
+
+    fn profile(id: i64) -> UserProfile {
+        UserProfile { id, username: format!("user{}", id), display_name: None, avatar_url: None, bio: None, status_text: None }
+    }
+
+    fn connect(server: &Addr<ChatServer>, user_id: i64, blocked: &[i64]) -> Arc<Mutex<Vec<String>>> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let addr = Collector { received: received.clone() }.start();
+        server.do_send(Connect {
+            user_id,
+            profile: profile(user_id),
+            blocked: blocked.iter().copied().collect(),
+            addr: addr.clone().recipient(),
+            closer: addr.recipient(),
+        });
+        received
+    }
+
+    #[actix::test]
+    async fn broadcast_skips_members_who_blocked_sender() {
+        // 不会真的连数据库，Broadcast 不查库
+        let pool = MySqlPoolOptions::new().connect_lazy("mysql://localhost/easynote").unwrap();
+        let server = ChatServer {
+            rooms: HashMap::from([(10, HashSet::from([1, 2, 3]))]),
+            ..ChatServer::new(pool)
+        }.start();
+
+        let blocker = connect(&server, 1, &[2]);
+        connect(&server, 2, &[]);
+        let other = connect(&server, 3, &[]);
+
+        server.send(Broadcast { conversation_id: 10, sender_id: 2, msg: "hi".to_string() }).await.unwrap();
+        actix::clock::sleep(Duration::from_millis(50)).await;
+
+        assert!(blocker.lock().unwrap().is_empty());
+        assert_eq!(*other.lock().unwrap(), vec!["hi".to_string()]);
+    }
+}
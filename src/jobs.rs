@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use sqlx::MySqlPool;
+
+use crate::services::{AccountDeletionService, AttachmentService, NoteService};
+use crate::utils::Storage;
+
+/// 后台定时任务，跑在 actix 的运行时上，和 HTTP 服务同进程
+pub fn spawn(pool: MySqlPool, storage: Storage, trash_retention_days: i64) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            run_once(&pool, &storage, trash_retention_days).await;
+        }
+    });
+}
+
+async fn run_once(pool: &MySqlPool, storage: &Storage, trash_retention_days: i64) {
+    match AccountDeletionService::purge_due(pool, storage).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("已清理 {} 个到期注销的账号", count),
+        Err(e) => tracing::error!("账号注销清理失败：{}", e),
+    }
+
+    match NoteService::purge_expired_trash(pool, trash_retention_days).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("已从回收站彻底删除 {} 篇笔记", count),
+        Err(e) => tracing::error!("回收站清理失败：{}", e),
+    }
+
+    // 放在回收站和注销清理之后，同一轮就能删掉它们留下的附件
+    match AttachmentService::purge_orphans(pool, storage).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("已清理 {} 个无主附件", count),
+        Err(e) => tracing::error!("附件清理失败：{}", e),
+    }
+}
@@ -6,9 +6,12 @@ mod services;
 mod handlers;
 mod utils;
 mod middleware;
+mod jobs;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, MailerKind};
 use crate::handlers::{ ChatServer };
+use crate::utils::{Storage, Mailer, LogMailer, FileMailer, OidcClient};
+use std::sync::Arc;
 use actix::Actor;  // 需要导入 trait 才能使用 .start()
 
 use actix_web::{App, HttpServer, web};
@@ -39,6 +42,14 @@ async fn main() -> std::io::Result<()> {
 
     tracing::info!("✅ Database connected");
 
+    let storage = web::Data::new(Storage::new(config.upload_dir.clone()));
+    let mailer: web::Data<dyn Mailer> = match config.mailer {
+        MailerKind::Log => web::Data::from(Arc::new(LogMailer) as Arc<dyn Mailer>),
+        MailerKind::File => web::Data::from(Arc::new(FileMailer { dir: config.mail_dir.clone().into() }) as Arc<dyn Mailer>),
+    };
+    let oidc = config.oidc.clone().map(|oidc| web::Data::new(OidcClient::new(oidc)));
+    // 到期注销账号、过期回收站、无主附件的清理等定时任务
+    jobs::spawn(pool.clone(), storage.get_ref().clone(), config.note_trash_retention_days);
     let config_data = web::Data::new(config);
 
     // 在闭包外创建，所有 worker 共享同一个 ChatServer
@@ -58,13 +69,18 @@ async fn main() -> std::io::Result<()> {
         // .allowed_headers(vec!["Content-Type", "Authorization"])
         // .max_age(3600);
 
-        App::new()
+        let mut app = App::new()
             .wrap(TracingLogger::default())
             .wrap(cors)
             .app_data(config_data.clone())
+            .app_data(storage.clone())
+            .app_data(mailer.clone())
             .app_data(web::Data::new(pool.clone()))
-            .app_data(web::Data::new(chat_server.clone()))  // clone Addr
-            .configure(routes::configure)
+            .app_data(web::Data::new(chat_server.clone()));  // clone Addr
+        if let Some(oidc) = &oidc {
+            app = app.app_data(oidc.clone());
+        }
+        app.configure(routes::configure)
     })
     .bind(&addr)?
     .run()
@@ -1,14 +1,36 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse,
+    http::Method,
+    web, Error, HttpMessage, HttpResponse,
     body::EitherBody,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
+use sqlx::MySqlPool;
 use std::rc::Rc;
-use crate::utils::JwtUtil;
+use crate::error::AppError;
+use crate::services::{ApiTokenService, UserService, API_TOKEN_PREFIX};
+use crate::utils::{Claims, JwtUtil};
 
+/// 个人访问令牌访问一组路由需要的权限：GET / HEAD 需要 read，其他方法需要 write
+#[derive(Debug, Clone, Copy)]
+pub struct ApiScope {
+    pub read: &'static str,
+    pub write: &'static str,
+}
+
+impl ApiScope {
+    pub const NOTES: ApiScope = ApiScope { read: "notes:read", write: "notes:write" };
+    pub const CHAT: ApiScope = ApiScope { read: "chat:read", write: "chat:send" };
+
+    fn required(&self, method: &Method) -> &'static str {
+        if method == Method::GET || method == Method::HEAD { self.read } else { self.write }
+    }
+}
+
+/// scope 为 None 的路由只接受 JWT，个人访问令牌一律 403
 pub struct Auth {
     pub jwt_secret: String,
+    pub scope: Option<ApiScope>,
 }
 
 impl<S, B> Transform<S, ServiceRequest> for Auth
@@ -26,6 +48,7 @@ where
         ok(AuthMiddleware {
             service: Rc::new(service),
             jwt_secret: self.jwt_secret.clone(),
+            scope: self.scope,
         })
     }
 }
@@ -33,6 +56,17 @@ where
 pub struct AuthMiddleware<S> {
     service: Rc<S>,
     jwt_secret: String,
+    scope: Option<ApiScope>,
+}
+
+impl<S> AuthMiddleware<S> {
+    fn disabled_response() -> HttpResponse {
+        HttpResponse::Forbidden()
+            .json(serde_json::json!({
+                "code": 403,
+                "message": "Account disabled"
+            }))
+    }
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
@@ -49,6 +83,7 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let service = self.service.clone();
         let jwt_secret = self.jwt_secret.clone();
+        let scope = self.scope;
 
         Box::pin(async move {
             // 从 Header 提取 Token
@@ -69,21 +104,93 @@ where
                 }
             };
 
-            // 验证 Token
-            match JwtUtil::verify_token(token, &jwt_secret) {
-                Ok(claims) => {
-                    req.extensions_mut().insert(claims);
-                    service.call(req).await.map(|res| res.map_into_left_body())
+            // 个人访问令牌：校验路由要求的权限；改密码、强制下线等操作会直接吊销令牌
+            if token.starts_with(API_TOKEN_PREFIX) {
+                let Some(pool) = req.app_data::<web::Data<MySqlPool>>().cloned() else {
+                    return Err(AppError::Internal("database pool missing".to_string()).into());
+                };
+                let (user_id, scopes) = match ApiTokenService::authenticate(pool.get_ref(), token).await? {
+                    Some(found) => found,
+                    None => {
+                        let response = HttpResponse::Unauthorized()
+                            .json(serde_json::json!({
+                                "code": 401,
+                                "message": "Invalid token"
+                            }));
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                };
+
+                let required = scope.map(|scope| scope.required(req.method()));
+                if !required.is_some_and(|required| scopes.iter().any(|s| s == required)) {
+                    let response = HttpResponse::Forbidden()
+                        .json(serde_json::json!({
+                            "code": 403,
+                            "message": match required {
+                                Some(required) => format!("Token missing scope: {}", required),
+                                None => "API tokens are not allowed here".to_string(),
+                            }
+                        }));
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+
+                let user = match UserService::find_by_id(pool.get_ref(), user_id).await {
+                    Ok(user) => user,
+                    Err(AppError::NotFound(_)) => {
+                        let response = HttpResponse::Unauthorized()
+                            .json(serde_json::json!({
+                                "code": 401,
+                                "message": "Token revoked"
+                            }));
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                if user.disabled_at.is_some() {
+                    return Ok(req.into_response(Self::disabled_response()).map_into_right_body());
                 }
+
+                req.extensions_mut().insert(Claims { sub: user_id, exp: 0, ver: user.token_version });
+                req.extensions_mut().insert(user.role);
+                return service.call(req).await.map(|res| res.map_into_left_body());
+            }
+
+            // 验证 Token
+            let claims = match JwtUtil::verify_token(token, &jwt_secret) {
+                Ok(claims) => claims,
                 Err(_) => {
                     let response = HttpResponse::Unauthorized()
                         .json(serde_json::json!({
                             "code": 401,
                             "message": "Invalid token"
                         }));
-                    Ok(req.into_response(response).map_into_right_body())
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            };
+
+            // 改密码 / 重置密码 / 强制下线后 token_version 会变，旧 Token 作废；被禁用的账号直接拒绝
+            if let Some(pool) = req.app_data::<web::Data<MySqlPool>>().cloned() {
+                match UserService::find_by_id(pool.get_ref(), claims.sub).await {
+                    Ok(user) if user.token_version == claims.ver => {
+                        if user.disabled_at.is_some() {
+                            return Ok(req.into_response(Self::disabled_response()).map_into_right_body());
+                        }
+                        req.extensions_mut().insert(user.role);
+                    }
+                    Ok(_) | Err(AppError::NotFound(_)) => {
+                        let response = HttpResponse::Unauthorized()
+                            .json(serde_json::json!({
+                                "code": 401,
+                                "message": "Token revoked"
+                            }));
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                    Err(e) => return Err(e.into()),
                 }
             }
+
+            req.extensions_mut().insert(claims);
+            service.call(req).await.map(|res| res.map_into_left_body())
         })
     }
 }
\ No newline at end of file
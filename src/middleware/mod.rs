@@ -1,3 +1,5 @@
 mod auth;
+mod role;
 
-pub use auth::Auth;
\ No newline at end of file
+pub use auth::{Auth, ApiScope};
+pub use role::RequireRole;
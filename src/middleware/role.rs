@@ -0,0 +1,67 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage, HttpResponse,
+    body::EitherBody,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use crate::models::Role;
+
+/// 要求指定系统角色，必须放在 `Auth` 里面 (先 `.wrap(RequireRole)` 再 `.wrap(Auth)`)，
+/// 角色由 `Auth` 加载用户时写入请求扩展
+pub struct RequireRole(pub Role);
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireRoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireRoleMiddleware {
+            service: Rc::new(service),
+            role: self.0,
+        })
+    }
+}
+
+pub struct RequireRoleMiddleware<S> {
+    service: Rc<S>,
+    role: Role,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let role = self.role;
+
+        Box::pin(async move {
+            let allowed = req.extensions().get::<Role>().is_some_and(|r| *r == role);
+            if !allowed {
+                let response = HttpResponse::Forbidden()
+                    .json(serde_json::json!({
+                        "code": 403,
+                        "message": "Permission denied"
+                    }));
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+
+            service.call(req).await.map(|res| res.map_into_left_body())
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use validator::{Validate, ValidationError};
+
+/// 个人访问令牌可以申请的权限
+pub const API_SCOPES: [&str; 4] = ["notes:read", "notes:write", "chat:read", "chat:send"];
+
+/// 列表里展示的令牌信息，明文只在创建时返回一次
+#[derive(Debug, FromRow, Serialize)]
+pub struct ApiToken {
+  pub id: i64,
+  pub name: String,
+  /// 令牌前几位，方便用户分辨
+  pub token_prefix: String,
+  pub scopes: Json<Vec<String>>,
+  pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+  pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+  pub created_at: chrono::DateTime<chrono::Utc>
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+  if scopes.iter().all(|s| API_SCOPES.contains(&s.as_str())) {
+    Ok(())
+  } else {
+    Err(ValidationError::new("unknown_scope"))
+  }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiToken {
+  #[validate(length(min = 1, max = 64))]
+  pub name: String,
+  #[validate(length(min = 1), custom(function = "validate_scopes"))]
+  pub scopes: Vec<String>,
+  /// 不传表示永不过期
+  #[validate(range(min = 1, max = 3650))]
+  pub expires_in_days: Option<i64>,
+}
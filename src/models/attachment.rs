@@ -0,0 +1,46 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// 附件公开地址前缀，后面接 token
+pub const ATTACHMENT_URL_PREFIX: &str = "/files/attachments/";
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct NoteAttachment {
+    pub id: i64,
+    pub note_id: i64,
+    #[serde(skip_serializing)]
+    pub token: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 不需要登录的固定地址，可以直接写进 Markdown
+    #[sqlx(skip)]
+    pub url: String,
+    /// 插入正文用的 Markdown，图片是 `![]()`，其他是链接
+    #[sqlx(skip)]
+    pub markdown: String,
+}
+
+impl NoteAttachment {
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+}
+
+/// 上传的文件，由 handler 从请求里组装
+#[derive(Debug)]
+pub struct NewAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// 当前用户的附件空间
+#[derive(Debug, Serialize)]
+pub struct AttachmentUsage {
+    pub used_bytes: i64,
+    pub quota_bytes: i64,
+}
@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{ Deserialize, Serialize };
 use sqlx::FromRow;
 use chrono::{ DateTime, Utc };
 
@@ -36,7 +36,22 @@ pub struct ConversationMember {
   pub conversation_id: i64,
   pub user_id: i64,
   pub role: MemberRole,
-  pub joined_at: DateTime<Utc>
+  pub joined_at: DateTime<Utc>,
+  pub is_pinned: bool,
+  pub pinned_at: Option<DateTime<Utc>>,
+  pub is_archived: bool,
+  pub is_hidden: bool,
+  pub is_muted: bool,
+  pub cleared_at: Option<DateTime<Utc>>
+}
+
+/// 成员对会话的个人设置，字段为 None 表示不修改
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberSettings {
+  pub pinned: Option<bool>,
+  pub archived: Option<bool>,
+  pub hidden: Option<bool>,
+  pub muted: Option<bool>
 }
 
 #[derive(Debug, Clone, sqlx::Type)]
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::Note;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct Folder {
+    pub id: i64,
+    pub user_id: i64,
+    pub parent_id: Option<i64>,
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Folder {
+    /// 导出 zip 里的目录名，规则和 `Note::file_stem` 一样
+    pub fn dir_name(&self) -> String {
+        let name: String = self.name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+            .take(50)
+            .collect();
+        format!("{}-{}", self.id, name.trim_matches('-'))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFolder {
+    #[validate(length(min = 1, max = 128))]
+    pub name: String,
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenameFolder {
+    #[validate(length(min = 1, max = 128))]
+    pub name: String,
+}
+
+/// 移动笔记或文件夹，目标为 null 表示移到根目录
+#[derive(Debug, Deserialize)]
+pub struct MoveTarget {
+    pub folder_id: Option<i64>,
+}
+
+/// 文件夹内容：直接子文件夹 + 直接包含的笔记
+#[derive(Debug, Serialize)]
+pub struct FolderContents {
+    pub folder: Option<Folder>,
+    pub folders: Vec<Folder>,
+    pub notes: Vec<Note>,
+}
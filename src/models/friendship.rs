@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use validator::Validate;
 
 #[derive(Debug, Clone, sqlx::Type, Serialize)]
 #[sqlx(type_name = "ENUM", rename_all = "lowercase")]
@@ -16,6 +17,27 @@ pub struct FriendShip {
   pub requester_id: i64,
   pub receiver_id: i64,
   pub status: FriendShipStatus,
+  pub message: Option<String>,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct FriendLabel {
+  pub id: i64,
+  pub user_id: i64,
+  pub name: String,
+  pub created_at: DateTime<Utc>
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct LabelReq {
+  #[validate(length(min = 1, max = 64))]
+  pub name: String
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct LabelMembersReq {
+  #[validate(length(min = 1, max = 500))]
+  pub friend_ids: Vec<i64>
 }
\ No newline at end of file
@@ -1,9 +1,21 @@
 mod note;
+mod folder;
+mod tag;
+mod publication;
+mod attachment;
+mod template;
 mod user;
 mod conversation;
 mod friendship;
+mod api_token;
 
-pub use note::{Note, CreateNote, UpdateNote};
-pub use user::{User, RegisterRequest};
-pub use conversation::{ ConversationType, MemberRole };
-pub use friendship::{ FriendShip, FriendShipStatus };
\ No newline at end of file
+pub use note::{Note, ContentFormat, CreateNote, UpdateNote, ShareNote, RenderedNote, NoteExportFormat, ImportFormat, NoteCard, SharedNote, ShareNoteToConversation, NoteLink, LinkedNote, BrokenLink, LinkEdge, NoteGraph};
+pub use tag::{Tag, RenameTag, MergeTags};
+pub use publication::{NotePublication, PublishNote, PublicNote};
+pub use attachment::{NoteAttachment, NewAttachment, AttachmentUsage, ATTACHMENT_URL_PREFIX};
+pub use template::{NoteTemplate, CreateTemplate, UpdateTemplate};
+pub use folder::{Folder, CreateFolder, RenameFolder, MoveTarget, FolderContents};
+pub use user::{User, UserProfile, PublicUser, RegisterRequest, UpdateProfile, UpdatePrivacy, ChangePassword, ForgotPassword, ResetPassword, VerifyEmail, TwoFactorCode, DisableTwoFactor, UserIdentity, Role, DeleteAccount, validate_password};
+pub use conversation::{ ConversationType, MemberRole, UpdateMemberSettings };
+pub use friendship::{ FriendShip, FriendShipStatus, FriendLabel, LabelReq, LabelMembersReq };
+pub use api_token::{ ApiToken, CreateApiToken };
\ No newline at end of file
@@ -1,24 +1,211 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use validator::Validate;
+
+use crate::utils::Markdown;
+
+/// 列表页摘要的最大字符数
+const EXCERPT_CHARS: usize = 200;
+
+/// 笔记正文格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    Plain,
+    #[default]
+    Markdown,
+}
+
+impl ContentFormat {
+    /// 清洗过的 HTML，可以直接插入页面
+    pub fn render_html(self, content: &str) -> String {
+        match self {
+            ContentFormat::Plain => Markdown::render_plain(content),
+            ContentFormat::Markdown => Markdown::render_html(content),
+        }
+    }
+
+    pub fn excerpt(self, content: &str) -> String {
+        match self {
+            ContentFormat::Plain => Markdown::plain_excerpt(content, EXCERPT_CHARS),
+            ContentFormat::Markdown => Markdown::excerpt(content, EXCERPT_CHARS),
+        }
+    }
+}
 
 #[derive(Debug, FromRow, Serialize)]
 pub struct Note {
     pub id: i64,
     pub title: String,
     pub content: Option<String>,
+    pub content_format: ContentFormat,
+    /// 纯文本摘要，保存时由正文生成
+    pub excerpt: Option<String>,
     pub user_id: i64,
+    pub folder_id: Option<i64>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// 不为空表示在回收站里
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 由 NoteService 查询后填充
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Note {
+    pub fn render_html(&self) -> String {
+        self.content_format.render_html(self.content.as_deref().unwrap_or(""))
+    }
+
+    /// 导出用的 Markdown：YAML front matter + 正文
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "---\ntitle: {}\ntags: {}\ncreated_at: {}\nupdated_at: {}\n---\n\n{}\n",
+            serde_json::to_string(&self.title).unwrap_or_default(),
+            serde_json::to_string(&self.tags).unwrap_or_default(),
+            self.created_at.to_rfc3339(),
+            self.updated_at.to_rfc3339(),
+            self.content.as_deref().unwrap_or("")
+        )
+    }
+
+    /// 导出文件名 `{id}-{标题}`，去掉文件名里不能用的字符
+    pub fn file_stem(&self) -> String {
+        let title: String = self.title
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+            .take(50)
+            .collect();
+        format!("{}-{}", self.id, title.trim_matches('-'))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateNote {
+    /// 用模板创建时可以不传，按模板的标题生成
+    #[serde(default)]
+    #[validate(length(min = 1, max = 200))]
     pub title: String,
+    #[validate(length(max = 1000000))]
     pub content: Option<String>,
+    #[serde(default)]
+    pub content_format: ContentFormat,
+    /// 为空时放在根目录
+    pub folder_id: Option<i64>,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateNote {
+    #[validate(length(min = 1, max = 200))]
     pub title: Option<String>,
+    #[validate(length(max = 1000000))]
     pub content: Option<String>,
+    pub content_format: Option<ContentFormat>,
+    /// 传了就整体替换
+    #[validate(length(max = 50))]
+    pub tags: Option<Vec<String>>,
+}
+
+/// 聊天里的笔记卡片，只包含标题、摘要和作者
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct NoteCard {
+    pub id: i64,
+    pub title: String,
+    pub excerpt: Option<String>,
+    pub owner_id: i64,
+    pub owner_name: String,
+    pub owner_avatar_url: Option<String>,
+}
+
+/// 会话里分享过的笔记
+#[derive(Debug, FromRow, Serialize)]
+pub struct SharedNote {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub card: NoteCard,
+    pub shared_by: i64,
+    pub shared_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareNoteToConversation {
+    pub note_id: i64,
+}
+
+/// 正文里的 `[[...]]` 链接；目标不存在或在回收站里时 target_id 和 target_title 为空 (断链)
+#[derive(Debug, FromRow, Serialize)]
+pub struct NoteLink {
+    pub target: String,
+    pub target_id: Option<i64>,
+    pub target_title: Option<String>,
+}
+
+/// 反向链接、关系图里的笔记
+#[derive(Debug, FromRow, Serialize)]
+pub struct LinkedNote {
+    pub id: i64,
+    pub title: String,
+    pub excerpt: Option<String>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct BrokenLink {
+    pub source_id: i64,
+    pub source_title: String,
+    pub target: String,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct LinkEdge {
+    pub source_id: i64,
+    pub target_id: i64,
+}
+
+/// 自己全部笔记的链接关系图，只包含能解析的链接
+#[derive(Debug, Serialize)]
+pub struct NoteGraph {
+    pub nodes: Vec<LinkedNote>,
+    pub edges: Vec<LinkEdge>,
+}
+
+/// 单篇导出格式 `GET /notes/{id}/export?format=`
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteExportFormat {
+    Md,
+    Html,
+    Pdf,
+}
+
+/// 导入格式 `POST /notes/import?format=`：单个 Markdown、Markdown 的 zip 包、Evernote ENEX
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Md,
+    Zip,
+    Enex,
+}
+
+/// `GET /notes/{id}?render=html` 的返回：笔记本身 + 渲染好的 HTML
+#[derive(Debug, Serialize)]
+pub struct RenderedNote {
+    #[serde(flatten)]
+    pub note: Note,
+    pub html: String,
+}
+
+/// 共享目标：直接指定用户，或者指定好友标签 (共享时展开为标签内的好友)
+/// 笔记和文件夹共享都用它
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShareNote {
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub user_ids: Vec<i64>,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub label_ids: Vec<i64>,
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::ContentFormat;
+
+/// 笔记的公开链接
+#[derive(Debug, FromRow, Serialize)]
+pub struct NotePublication {
+    pub id: i64,
+    pub note_id: i64,
+    pub token: String,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub view_count: i64,
+    pub last_viewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 前端页面地址 `{APP_URL}/p/{token}`
+    #[sqlx(skip)]
+    pub url: String,
+    #[sqlx(skip)]
+    pub has_password: bool,
+}
+
+/// 发布或修改公开设置；再次发布时链接不变，密码和有效期整体替换
+#[derive(Debug, Deserialize, Validate)]
+pub struct PublishNote {
+    #[validate(length(min = 4, max = 128))]
+    pub password: Option<String>,
+    /// 为空时永久有效
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// 匿名访问看到的内容，不包含文件夹、标签等私人信息
+#[derive(Debug, Serialize)]
+pub struct PublicNote {
+    pub title: String,
+    pub content: Option<String>,
+    pub content_format: ContentFormat,
+    pub html: String,
+    pub author: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    /// 打了这个标签的笔记数
+    pub note_count: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenameTag {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+}
+
+/// 把 source_ids 合并进 target_id，合并后 source 标签被删除
+#[derive(Debug, Deserialize, Validate)]
+pub struct MergeTags {
+    #[validate(length(min = 1, max = 50))]
+    pub source_ids: Vec<i64>,
+    pub target_id: i64,
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::ContentFormat;
+
+/// 笔记模板；`user_id` 为空的是全局模板
+#[derive(Debug, FromRow, Serialize)]
+pub struct NoteTemplate {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub name: String,
+    pub title_pattern: String,
+    pub content: String,
+    pub content_format: ContentFormat,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTemplate {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, max = 200))]
+    pub title_pattern: String,
+    #[serde(default)]
+    #[validate(length(max = 1000000))]
+    pub content: String,
+    #[serde(default)]
+    pub content_format: ContentFormat,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTemplate {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 200))]
+    pub title_pattern: Option<String>,
+    #[validate(length(max = 1000000))]
+    pub content: Option<String>,
+    pub content_format: Option<ContentFormat>,
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use validator::{Validate, ValidationError};
 
 #[derive(Debug, FromRow, Serialize)]
 pub struct User {
@@ -9,13 +10,232 @@ pub struct User {
   #[sqlx(rename = "password")]
   pub password_hash: String,
   pub email: Option<String>,
+  pub phone: Option<String>,
+  pub display_name: Option<String>,
+  pub avatar_url: Option<String>,
+  pub bio: Option<String>,
+  pub status_text: Option<String>,
+  pub username_search: Visibility,
+  pub contact_search: Visibility,
+  pub email_visibility: Visibility,
+  #[serde(skip_serializing)]
+  pub token_version: i32,
+  pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+  #[serde(skip_serializing)]
+  pub totp_secret: Option<String>,
+  /// 最后一次用过的 TOTP 时间窗口，更早或相同的窗口不再接受
+  #[serde(skip_serializing)]
+  pub totp_last_step: Option<i64>,
+  /// 两步验证开启时间，为空表示未开启
+  pub totp_enabled_at: Option<chrono::DateTime<chrono::Utc>>,
+  /// 管理员要求该用户必须开启两步验证
+  pub two_factor_required: bool,
+  pub role: Role,
+  /// 被管理员禁用的时间，为空表示正常
+  pub disabled_at: Option<chrono::DateTime<chrono::Utc>>,
+  pub disabled_reason: Option<String>,
+  /// 申请注销后计划删除的时间，宽限期内可以撤销
+  pub deletion_scheduled_at: Option<chrono::DateTime<chrono::Utc>>,
   pub created_at: chrono::DateTime<chrono::Utc>,
   pub updated_at: chrono::DateTime<chrono::Utc>
 }
 
-#[derive(Debug, Deserialize)]
+/// 系统角色
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+  User,
+  Admin
+}
+
+/// 隐私设置的可见范围
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+  Everyone,
+  Friends,
+  Nobody
+}
+
+impl Visibility {
+  pub fn allows(self, is_friend: bool) -> bool {
+    match self {
+      Visibility::Everyone => true,
+      Visibility::Friends => is_friend,
+      Visibility::Nobody => false,
+    }
+  }
+}
+
+/// 对外展示的用户资料，聊天消息、好友请求里嵌入的都是这个
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct UserProfile {
+  pub id: i64,
+  pub username: String,
+  pub display_name: Option<String>,
+  pub avatar_url: Option<String>,
+  pub bio: Option<String>,
+  pub status_text: Option<String>
+}
+
+impl From<User> for UserProfile {
+  fn from(user: User) -> Self {
+    UserProfile {
+      id: user.id,
+      username: user.username,
+      display_name: user.display_name,
+      avatar_url: user.avatar_url,
+      bio: user.bio,
+      status_text: user.status_text
+    }
+  }
+}
+
+/// 给其他用户看的资料：邮箱和手机号按 email_visibility 决定是否返回
+#[derive(Debug, Serialize)]
+pub struct PublicUser {
+  #[serde(flatten)]
+  pub profile: UserProfile,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub email: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub phone: Option<String>
+}
+
+impl PublicUser {
+  /// is_friend：查看者是否是该用户的好友
+  pub fn from_user(user: User, is_friend: bool) -> Self {
+    let show_contact = user.email_visibility.allows(is_friend);
+    let (email, phone) = if show_contact { (user.email.clone(), user.phone.clone()) } else { (None, None) };
+    PublicUser { profile: UserProfile::from(user), email, phone }
+  }
+}
+
+/// 用户名只允许字母、数字、`_` `.` `-`
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+  if username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+    Ok(())
+  } else {
+    Err(ValidationError::new("invalid_chars"))
+  }
+}
+
+/// bcrypt 只用前 72 字节，按字节而不是字符限制，避免中文等多字节密码被截断
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+  if password.len() <= 72 {
+    Ok(())
+  } else {
+    Err(ValidationError::new("max_bytes"))
+  }
+}
+
+/// 密码强度由 AppConfig.password_policy 另外校验，这里只限制最大长度
+#[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
+  #[validate(length(min = 3, max = 32), custom(function = "validate_username"))]
   pub username: String,
+  #[validate(custom(function = "validate_password"))]
+  pub password: String,
+  #[validate(email, length(max = 255))]
+  pub email: Option<String>,
+}
+
+/// 修改资料：None 表示不修改，空字符串表示清空
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfile {
+  #[validate(length(max = 64))]
+  pub display_name: Option<String>,
+  #[validate(length(max = 500))]
+  pub bio: Option<String>,
+  #[validate(length(max = 100))]
+  pub status_text: Option<String>,
+  #[validate(length(max = 32))]
+  pub phone: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePassword {
+  #[validate(length(min = 1))]
+  pub current_password: String,
+  #[validate(custom(function = "validate_password"))]
+  pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPassword {
+  #[validate(email)]
+  pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPassword {
+  #[validate(length(min = 1))]
+  pub token: String,
+  #[validate(custom(function = "validate_password"))]
+  pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyEmail {
+  #[validate(length(min = 1))]
+  pub token: String,
+}
+
+/// 修改隐私设置，None 表示不修改
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacy {
+  pub username_search: Option<Visibility>,
+  pub contact_search: Option<Visibility>,
+  pub email_visibility: Option<Visibility>,
+}
+#[derive(Debug, Deserialize, Validate)]
+pub struct TwoFactorCode {
+  /// 身份验证器里的 6 位数字，或者一次性恢复码
+  #[validate(length(min = 6, max = 16))]
+  pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisableTwoFactor {
+  #[validate(length(min = 1))]
   pub password: String,
+  #[validate(length(min = 6, max = 16))]
+  pub code: String,
+}
+
+/// 关联的外部身份 (OIDC)
+#[derive(Debug, FromRow, Serialize)]
+pub struct UserIdentity {
+  pub id: i64,
+  pub issuer: String,
+  pub subject: String,
   pub email: Option<String>,
-}
\ No newline at end of file
+  pub created_at: chrono::DateTime<chrono::Utc>
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DeleteAccount {
+  /// 允许密码登录时必须提供
+  #[validate(length(min = 1), custom(function = "validate_password"))]
+  pub password: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::validate_password;
+
+  #[test]
+  fn password_limit_is_72_bytes() {
+    assert!(validate_password(&"a".repeat(72)).is_ok());
+    assert!(validate_password(&"a".repeat(73)).is_err());
+  }
+
+  #[test]
+  fn password_limit_counts_bytes_not_chars() {
+    // 24 个汉字正好 72 字节，25 个超出
+    assert!(validate_password(&"密".repeat(24)).is_ok());
+    assert!(validate_password(&"密".repeat(25)).is_err());
+  }
+}
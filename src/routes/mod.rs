@@ -9,7 +9,12 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         .configure(ws::configure)
         .configure(crate::handlers::auth_configure)
         .configure(crate::handlers::note_configure)
+        .configure(crate::handlers::folder_configure)
+        .configure(crate::handlers::tag_configure)
         .configure(crate::handlers::conversation_configure)
         .configure(crate::handlers::friendship_configure)
-        .configure(crate::handlers::user_configure);
+        .configure(crate::handlers::user_configure)
+        .configure(crate::handlers::files_configure)
+        .configure(crate::handlers::admin_configure)
+        .configure(crate::handlers::public_configure);
 }
\ No newline at end of file
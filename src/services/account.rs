@@ -0,0 +1,146 @@
+use chrono::Duration;
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::models::User;
+use crate::services::{ApiTokenService, UserService};
+use crate::utils::{Mail, Mailer, TokenUtil};
+
+const PURPOSE_PASSWORD_RESET: &str = "password_reset";
+const PURPOSE_EMAIL_VERIFY: &str = "email_verify";
+
+/// 账号安全相关：改密码、找回密码、邮箱验证
+pub struct AccountService;
+
+impl AccountService {
+  /// 修改密码，返回新的 token_version，调用方用它给当前会话签发新 token
+  pub async fn change_password(pool: &MySqlPool, user_id: i64, current_password: &str, new_password: &str) -> Result<i32, AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    if !UserService::verify_password(current_password, &user.password_hash)? {
+      return Err(AppError::Unauthorized("当前密码错误".to_string()));
+    }
+    let token_version = UserService::set_password(pool, user_id, new_password).await?;
+    ApiTokenService::revoke_all(pool, user_id).await?;
+    Ok(token_version)
+  }
+
+  /// 只发给已验证的邮箱；不管邮箱是否存在都返回成功，避免通过这个接口探测邮箱
+  pub async fn request_password_reset(pool: &MySqlPool, mailer: &dyn Mailer, app_url: &str, email: &str) -> Result<(), AppError> {
+    let Some(user) = UserService::find_by_verified_email(pool, email).await? else {
+      return Ok(());
+    };
+
+    // 新令牌生成后旧的全部作废
+    Self::revoke_tokens(pool, user.id, PURPOSE_PASSWORD_RESET).await?;
+    let token = Self::issue_token(pool, user.id, PURPOSE_PASSWORD_RESET, Duration::minutes(30)).await?;
+
+    // 发送失败只记日志，返回错误会暴露该邮箱已注册
+    if let Err(e) = mailer.send(Mail {
+      to: email.to_string(),
+      subject: "重置密码".to_string(),
+      body: format!("点击链接重置密码 (30 分钟内有效)：{}/reset-password?token={}", app_url, token),
+    }).await {
+      tracing::error!("重置密码邮件发送失败：{}", e);
+    }
+    Ok(())
+  }
+
+  /// 返回用户 id，调用方负责断开 WebSocket
+  pub async fn reset_password(pool: &MySqlPool, token: &str, new_password: &str) -> Result<i64, AppError> {
+    let user_id = Self::consume_token(pool, PURPOSE_PASSWORD_RESET, token).await?;
+    UserService::set_password(pool, user_id, new_password).await?;
+    ApiTokenService::revoke_all(pool, user_id).await?;
+    Ok(user_id)
+  }
+
+  pub async fn send_email_verification(pool: &MySqlPool, mailer: &dyn Mailer, app_url: &str, user: &User) -> Result<(), AppError> {
+    let Some(email) = user.email.as_deref() else {
+      return Err(AppError::BadRequest("未设置邮箱".to_string()));
+    };
+    if user.email_verified_at.is_some() {
+      return Err(AppError::BadRequest("邮箱已验证".to_string()));
+    }
+
+    Self::revoke_tokens(pool, user.id, PURPOSE_EMAIL_VERIFY).await?;
+    let token = Self::issue_token(pool, user.id, PURPOSE_EMAIL_VERIFY, Duration::hours(24)).await?;
+
+    mailer.send(Mail {
+      to: email.to_string(),
+      subject: "验证邮箱".to_string(),
+      body: format!("点击链接验证邮箱 (24 小时内有效)：{}/verify-email?token={}", app_url, token),
+    }).await
+  }
+
+  pub async fn verify_email(pool: &MySqlPool, token: &str) -> Result<(), AppError> {
+    let user_id = Self::consume_token(pool, PURPOSE_EMAIL_VERIFY, token).await?;
+
+    sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| {
+        if e.as_database_error().is_some_and(|d| d.is_unique_violation()) {
+          AppError::BadRequest("该邮箱已被其他账号验证".to_string())
+        } else {
+          AppError::Internal(e.to_string())
+        }
+      })?;
+
+    Ok(())
+  }
+
+  async fn issue_token(pool: &MySqlPool, user_id: i64, purpose: &str, ttl: Duration) -> Result<String, AppError> {
+    let token = TokenUtil::generate();
+
+    sqlx::query(
+      "INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(TokenUtil::hash(&token))
+    .bind(chrono::Utc::now() + ttl)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(token)
+  }
+
+  async fn revoke_tokens(pool: &MySqlPool, user_id: i64, purpose: &str) -> Result<(), AppError> {
+    sqlx::query(
+      "UPDATE user_tokens SET used_at = NOW() WHERE user_id = ? AND purpose = ? AND used_at IS NULL"
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 先 UPDATE 标记已使用再查 user_id，保证同一个令牌并发请求时只有一个成功
+  async fn consume_token(pool: &MySqlPool, purpose: &str, token: &str) -> Result<i64, AppError> {
+    let token_hash = TokenUtil::hash(token);
+
+    let result = sqlx::query(
+      "UPDATE user_tokens SET used_at = NOW()
+      WHERE token_hash = ? AND purpose = ? AND used_at IS NULL AND expires_at > NOW()"
+    )
+    .bind(&token_hash)
+    .bind(purpose)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::BadRequest("链接无效或已过期".to_string()));
+    }
+
+    sqlx::query_scalar::<_, i64>("SELECT user_id FROM user_tokens WHERE token_hash = ?")
+      .bind(&token_hash)
+      .fetch_one(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+}
\ No newline at end of file
@@ -0,0 +1,236 @@
+use serde::Serialize;
+use sqlx::{FromRow, MySqlPool, QueryBuilder};
+
+use crate::error::AppError;
+use crate::models::{CreateTemplate, NoteTemplate, Role, UpdateTemplate, User};
+use crate::services::{ApiTokenService, NoteService, TemplateService, UserService};
+use crate::utils::TokenUtil;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct AdminAction {
+  pub id: i64,
+  pub admin_id: i64,
+  pub action: String,
+  pub target_user_id: Option<i64>,
+  pub detail: Option<String>,
+  pub created_at: chrono::DateTime<chrono::Utc>
+}
+
+/// 管理员用户列表的筛选条件
+pub struct UserFilter<'a> {
+  /// 用户名 / 昵称 / 邮箱模糊匹配
+  pub q: Option<&'a str>,
+  pub role: Option<Role>,
+  pub disabled: Option<bool>,
+  pub limit: i64,
+  pub offset: i64,
+}
+
+/// 管理后台：用户管理和内容清理，所有写操作都记录到 admin_actions
+pub struct AdminService;
+
+impl AdminService {
+  pub async fn list_users(pool: &MySqlPool, filter: UserFilter<'_>) -> Result<Vec<User>, AppError> {
+    let mut query = QueryBuilder::new("SELECT * FROM users WHERE 1 = 1");
+    if let Some(q) = filter.q.map(str::trim).filter(|q| !q.is_empty()) {
+      let pattern = format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+      query.push(" AND (username LIKE ")
+        .push_bind(pattern.clone())
+        .push(" OR display_name LIKE ")
+        .push_bind(pattern.clone())
+        .push(" OR email LIKE ")
+        .push_bind(pattern)
+        .push(")");
+    }
+    if let Some(role) = filter.role {
+      query.push(" AND role = ").push_bind(role);
+    }
+    match filter.disabled {
+      Some(true) => { query.push(" AND disabled_at IS NOT NULL"); }
+      Some(false) => { query.push(" AND disabled_at IS NULL"); }
+      None => {}
+    }
+    query.push(" ORDER BY id DESC LIMIT ")
+      .push_bind(filter.limit)
+      .push(" OFFSET ")
+      .push_bind(filter.offset);
+
+    query.build_query_as::<User>()
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  pub async fn set_role(pool: &MySqlPool, admin_id: i64, user_id: i64, role: Role) -> Result<User, AppError> {
+    Self::ensure_not_self(admin_id, user_id)?;
+    UserService::find_by_id(pool, user_id).await?;
+
+    sqlx::query("UPDATE users SET role = ? WHERE id = ?")
+      .bind(role)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::log(pool, admin_id, "set_role", Some(user_id), Some(&format!("{:?}", role).to_lowercase())).await?;
+    UserService::find_by_id(pool, user_id).await
+  }
+
+  /// 禁用账号并让所有 JWT 失效，调用方负责断开 WebSocket
+  pub async fn disable(pool: &MySqlPool, admin_id: i64, user_id: i64, reason: Option<&str>) -> Result<User, AppError> {
+    Self::ensure_not_self(admin_id, user_id)?;
+    UserService::find_by_id(pool, user_id).await?;
+
+    sqlx::query(
+      "UPDATE users SET disabled_at = NOW(), disabled_reason = ?, token_version = token_version + 1 WHERE id = ?"
+    )
+      .bind(reason)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::log(pool, admin_id, "disable", Some(user_id), reason).await?;
+    UserService::find_by_id(pool, user_id).await
+  }
+
+  pub async fn enable(pool: &MySqlPool, admin_id: i64, user_id: i64) -> Result<User, AppError> {
+    UserService::find_by_id(pool, user_id).await?;
+
+    sqlx::query("UPDATE users SET disabled_at = NULL, disabled_reason = NULL WHERE id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::log(pool, admin_id, "enable", Some(user_id), None).await?;
+    UserService::find_by_id(pool, user_id).await
+  }
+
+  /// token_version + 1，所有已签发的 JWT 失效，访问令牌全部吊销
+  pub async fn force_logout(pool: &MySqlPool, admin_id: i64, user_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query("UPDATE users SET token_version = token_version + 1 WHERE id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::NotFound("用户不存在".to_string()));
+    }
+    ApiTokenService::revoke_all(pool, user_id).await?;
+    Self::log(pool, admin_id, "force_logout", Some(user_id), None).await
+  }
+
+  /// 设置随机临时密码并返回，同时所有 JWT 失效
+  pub async fn reset_password(pool: &MySqlPool, admin_id: i64, user_id: i64) -> Result<String, AppError> {
+    UserService::find_by_id(pool, user_id).await?;
+    let password = TokenUtil::generate()[..16].to_string();
+    UserService::set_password(pool, user_id, &password).await?;
+    ApiTokenService::revoke_all(pool, user_id).await?;
+
+    Self::log(pool, admin_id, "reset_password", Some(user_id), None).await?;
+    Ok(password)
+  }
+
+  pub async fn set_two_factor_required(pool: &MySqlPool, admin_id: i64, user_id: i64, required: bool) -> Result<User, AppError> {
+    UserService::find_by_id(pool, user_id).await?;
+
+    sqlx::query("UPDATE users SET two_factor_required = ? WHERE id = ?")
+      .bind(required)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::log(pool, admin_id, "two_factor_required", Some(user_id), Some(if required { "true" } else { "false" })).await?;
+    UserService::find_by_id(pool, user_id).await
+  }
+
+  pub async fn delete_note(pool: &MySqlPool, admin_id: i64, note_id: i64) -> Result<(), AppError> {
+    let note = NoteService::find_by_id(pool, note_id).await?;
+    NoteService::purge(pool, note_id).await?;
+
+    Self::log(pool, admin_id, "delete_note", Some(note.user_id), Some(&format!("note {}", note_id))).await
+  }
+
+  /// 删除用户的全部笔记和聊天消息，返回 (笔记数, 消息数)
+  /// 笔记逐篇走 `NoteService::purge`，关联数据 (共享、公开链接、会话分享、双向链接等) 一并清理
+  pub async fn delete_user_content(pool: &MySqlPool, admin_id: i64, user_id: i64) -> Result<(u64, u64), AppError> {
+    UserService::find_by_id(pool, user_id).await?;
+
+    let note_ids = sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE user_id = ?")
+      .bind(user_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    for note_id in &note_ids {
+      NoteService::purge(pool, *note_id).await?;
+    }
+    let notes = note_ids.len() as u64;
+
+    let messages = sqlx::query("DELETE FROM messages WHERE sender_id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+      .rows_affected();
+
+    Self::log(pool, admin_id, "delete_content", Some(user_id), Some(&format!("{} notes, {} messages", notes, messages))).await?;
+    Ok((notes, messages))
+  }
+
+  pub async fn create_template(pool: &MySqlPool, admin_id: i64, data: CreateTemplate) -> Result<NoteTemplate, AppError> {
+    let template = TemplateService::create(pool, None, data).await?;
+    Self::log(pool, admin_id, "create_template", None, Some(&format!("template {}", template.id))).await?;
+    Ok(template)
+  }
+
+  pub async fn update_template(pool: &MySqlPool, admin_id: i64, id: i64, data: UpdateTemplate) -> Result<NoteTemplate, AppError> {
+    let template = TemplateService::update(pool, id, None, data).await?;
+    Self::log(pool, admin_id, "update_template", None, Some(&format!("template {}", id))).await?;
+    Ok(template)
+  }
+
+  /// 内置模板也可以删除
+  pub async fn delete_template(pool: &MySqlPool, admin_id: i64, id: i64) -> Result<(), AppError> {
+    let template = TemplateService::delete(pool, id, None).await?;
+    Self::log(pool, admin_id, "delete_template", None, Some(&format!("template {} {}", id, template.name))).await
+  }
+
+  pub async fn list_actions(pool: &MySqlPool, target_user_id: Option<i64>, limit: i64, offset: i64) -> Result<Vec<AdminAction>, AppError> {
+    sqlx::query_as::<_, AdminAction>(
+      "SELECT * FROM admin_actions WHERE (? IS NULL OR target_user_id = ?) ORDER BY id DESC LIMIT ? OFFSET ?"
+    )
+      .bind(target_user_id)
+      .bind(target_user_id)
+      .bind(limit)
+      .bind(offset)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  /// 防止管理员把自己禁用或降级后没人能管理
+  fn ensure_not_self(admin_id: i64, user_id: i64) -> Result<(), AppError> {
+    if admin_id == user_id {
+      return Err(AppError::BadRequest("不能对自己执行该操作".to_string()));
+    }
+    Ok(())
+  }
+
+  async fn log(pool: &MySqlPool, admin_id: i64, action: &str, target_user_id: Option<i64>, detail: Option<&str>) -> Result<(), AppError> {
+    tracing::info!("admin {} {} target={:?} {:?}", admin_id, action, target_user_id, detail);
+
+    sqlx::query("INSERT INTO admin_actions (admin_id, action, target_user_id, detail) VALUES (?, ?, ?, ?)")
+      .bind(admin_id)
+      .bind(action)
+      .bind(target_user_id)
+      .bind(detail)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,113 @@
+use sqlx::{MySqlPool, types::Json};
+
+use crate::error::AppError;
+use crate::models::{ApiToken, CreateApiToken};
+use crate::utils::TokenUtil;
+
+/// 个人访问令牌前缀，Auth 中间件据此区分 JWT 和访问令牌
+pub const API_TOKEN_PREFIX: &str = "enp_";
+
+/// 个人访问令牌：给脚本用的长期凭证，带权限范围
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+  /// 返回令牌信息和明文，明文只有这一次
+  pub async fn create(pool: &MySqlPool, user_id: i64, data: CreateApiToken) -> Result<(ApiToken, String), AppError> {
+    let token = format!("{}{}", API_TOKEN_PREFIX, TokenUtil::generate());
+    let mut scopes = data.scopes;
+    scopes.sort();
+    scopes.dedup();
+    let expires_at = data.expires_in_days.map(|days| chrono::Utc::now() + chrono::Duration::days(days));
+
+    let result = sqlx::query(
+      "INSERT INTO api_tokens (user_id, name, token_prefix, token_hash, scopes, expires_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+      .bind(user_id)
+      .bind(&data.name)
+      .bind(&token[..API_TOKEN_PREFIX.len() + 8])
+      .bind(TokenUtil::hash(&token))
+      .bind(Json(&scopes))
+      .bind(expires_at)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let api_token = Self::find_owned(pool, result.last_insert_id() as i64, user_id).await?;
+    Ok((api_token, token))
+  }
+
+  /// 只列出未吊销的令牌 (包括已过期的，方便用户清理)
+  pub async fn list(pool: &MySqlPool, user_id: i64) -> Result<Vec<ApiToken>, AppError> {
+    sqlx::query_as::<_, ApiToken>(
+      "SELECT id, name, token_prefix, scopes, last_used_at, expires_at, created_at
+      FROM api_tokens WHERE user_id = ? AND revoked_at IS NULL ORDER BY id DESC"
+    )
+      .bind(user_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  pub async fn revoke(pool: &MySqlPool, id: i64, user_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query("UPDATE api_tokens SET revoked_at = NOW() WHERE id = ? AND user_id = ? AND revoked_at IS NULL")
+      .bind(id)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::NotFound("令牌不存在".to_string()));
+    }
+    Ok(())
+  }
+
+  /// 改密码、重置密码、强制下线、申请注销时调用，全部令牌立即失效
+  pub async fn revoke_all(pool: &MySqlPool, user_id: i64) -> Result<(), AppError> {
+    sqlx::query("UPDATE api_tokens SET revoked_at = NOW() WHERE user_id = ? AND revoked_at IS NULL")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(())
+  }
+
+  /// 校验令牌，返回 (user_id, scopes)；无效、已吊销、已过期都返回 None
+  pub async fn authenticate(pool: &MySqlPool, token: &str) -> Result<Option<(i64, Vec<String>)>, AppError> {
+    let token_hash = TokenUtil::hash(token);
+
+    let row = sqlx::query_as::<_, (i64, i64, Json<Vec<String>>)>(
+      "SELECT id, user_id, scopes FROM api_tokens
+      WHERE token_hash = ? AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())"
+    )
+      .bind(&token_hash)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let Some((id, user_id, scopes)) = row else {
+      return Ok(None);
+    };
+
+    sqlx::query("UPDATE api_tokens SET last_used_at = NOW() WHERE id = ?")
+      .bind(id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Some((user_id, scopes.0)))
+  }
+
+  async fn find_owned(pool: &MySqlPool, id: i64, user_id: i64) -> Result<ApiToken, AppError> {
+    sqlx::query_as::<_, ApiToken>(
+      "SELECT id, name, token_prefix, scopes, last_used_at, expires_at, created_at
+      FROM api_tokens WHERE id = ? AND user_id = ? AND revoked_at IS NULL"
+    )
+      .bind(id)
+      .bind(user_id)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+      .ok_or_else(|| AppError::NotFound("令牌不存在".to_string()))
+  }
+}
@@ -0,0 +1,197 @@
+use sqlx::MySqlPool;
+use crate::error::AppError;
+use crate::models::{AttachmentUsage, NewAttachment, NoteAttachment, ATTACHMENT_URL_PREFIX};
+use crate::services::NoteService;
+use crate::utils::{random_urlsafe, Storage};
+
+const FILENAME_MAX_CHARS: usize = 255;
+const USED_BYTES: &str = "SELECT CAST(COALESCE(SUM(size), 0) AS SIGNED) FROM note_attachments WHERE user_id = ?";
+
+pub struct AttachmentService;
+
+impl AttachmentService {
+    /// 只有作者可以上传，大小计入作者的附件空间
+    pub async fn upload(
+        pool: &MySqlPool,
+        storage: &Storage,
+        quota_bytes: i64,
+        note_id: i64,
+        user_id: i64,
+        upload: NewAttachment,
+    ) -> Result<NoteAttachment, AppError> {
+        NoteService::find_owned(pool, note_id, user_id).await?;
+        let NewAttachment { filename, content_type, data } = upload;
+        let filename = Self::clean_filename(&filename);
+        let content_type = Self::clean_content_type(&content_type, &filename);
+
+        // 锁住用户行，同一用户的上传排队检查配额，并发上传不会一起超出
+        let size = data.len() as i64;
+        let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+        sqlx::query("SELECT id FROM users WHERE id = ? FOR UPDATE")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let used = sqlx::query_scalar::<_, i64>(USED_BYTES)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if used + size > quota_bytes {
+            return Err(AppError::BadRequest("附件空间不足".to_string()));
+        }
+
+        let token = random_urlsafe(24);
+        let storage_key = format!("attachments/{}/{}", user_id, token);
+        storage.save(&storage_key, data).await?;
+
+        let result = sqlx::query(
+            "INSERT INTO note_attachments (note_id, user_id, token, filename, content_type, size, storage_key)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(&token)
+            .bind(&filename)
+            .bind(&content_type)
+            .bind(size)
+            .bind(&storage_key)
+            .execute(&mut *tx)
+            .await;
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                storage.delete(&storage_key).await?;
+                return Err(AppError::Internal(e.to_string()));
+            }
+        };
+        if let Err(e) = tx.commit().await {
+            storage.delete(&storage_key).await?;
+            return Err(AppError::Internal(e.to_string()));
+        }
+
+        let attachment = sqlx::query_as::<_, NoteAttachment>("SELECT * FROM note_attachments WHERE id = ?")
+            .bind(result.last_insert_id() as i64)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(Self::with_url(attachment))
+    }
+
+    /// 能读笔记的人都能看到附件列表
+    pub async fn find_for_note(pool: &MySqlPool, note_id: i64, user_id: i64) -> Result<Vec<NoteAttachment>, AppError> {
+        NoteService::find_accessible(pool, note_id, user_id).await?;
+
+        let attachments = sqlx::query_as::<_, NoteAttachment>("SELECT * FROM note_attachments WHERE note_id = ? ORDER BY id")
+            .bind(note_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(attachments.into_iter().map(Self::with_url).collect())
+    }
+
+    /// 公开地址下载用；笔记在回收站里时按不存在处理
+    pub async fn find_by_token(pool: &MySqlPool, token: &str) -> Result<NoteAttachment, AppError> {
+        sqlx::query_as::<_, NoteAttachment>(
+            "SELECT a.* FROM note_attachments a JOIN notes n ON n.id = a.note_id
+             WHERE a.token = ? AND n.deleted_at IS NULL"
+        )
+            .bind(token)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map(Self::with_url)
+            .ok_or_else(|| AppError::NotFound("文件不存在".to_string()))
+    }
+
+    /// 删除后公开地址立即失效，正文里的引用不会自动去掉
+    pub async fn delete(pool: &MySqlPool, storage: &Storage, note_id: i64, id: i64, user_id: i64) -> Result<(), AppError> {
+        NoteService::find_owned(pool, note_id, user_id).await?;
+
+        let attachment = sqlx::query_as::<_, NoteAttachment>("SELECT * FROM note_attachments WHERE id = ? AND note_id = ?")
+            .bind(id)
+            .bind(note_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound("附件不存在".to_string()))?;
+
+        sqlx::query("DELETE FROM note_attachments WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        storage.delete(&attachment.storage_key).await
+    }
+
+    pub async fn usage(pool: &MySqlPool, user_id: i64, quota_bytes: i64) -> Result<AttachmentUsage, AppError> {
+        Ok(AttachmentUsage { used_bytes: Self::used_bytes(pool, user_id).await?, quota_bytes })
+    }
+
+    /// 笔记已被彻底删除 (包括注销账号) 的附件，删除文件和记录
+    pub async fn purge_orphans(pool: &MySqlPool, storage: &Storage) -> Result<usize, AppError> {
+        let orphans = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, storage_key FROM note_attachments WHERE note_id NOT IN (SELECT id FROM notes)"
+        )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for (id, storage_key) in &orphans {
+            storage.delete(storage_key).await?;
+            sqlx::query("DELETE FROM note_attachments WHERE id = ?")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        Ok(orphans.len())
+    }
+
+    async fn used_bytes(pool: &MySqlPool, user_id: i64) -> Result<i64, AppError> {
+        sqlx::query_scalar::<_, i64>(USED_BYTES)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    fn with_url(mut attachment: NoteAttachment) -> NoteAttachment {
+        attachment.url = format!("{}{}", ATTACHMENT_URL_PREFIX, attachment.token);
+        let label = attachment.filename.replace('[', "\\[").replace(']', "\\]");
+        attachment.markdown = if attachment.is_image() {
+            format!("![{}]({})", label, attachment.url)
+        } else {
+            format!("[{}]({})", label, attachment.url)
+        };
+        attachment
+    }
+
+    /// 只保留文件名部分，去掉控制字符
+    fn clean_filename(filename: &str) -> String {
+        let name: String = filename
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or("")
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(FILENAME_MAX_CHARS)
+            .collect();
+        match name.trim() {
+            "" | "." | ".." => "attachment".to_string(),
+            name => name.to_string(),
+        }
+    }
+
+    /// 请求头没给或格式不对时按扩展名推断
+    fn clean_content_type(content_type: &str, filename: &str) -> String {
+        let content_type = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        let valid = content_type.len() <= 128
+            && content_type.split_once('/').is_some_and(|(kind, sub)| !kind.is_empty() && !sub.is_empty());
+        if valid && content_type != "application/octet-stream" {
+            return content_type;
+        }
+        Storage::content_type(filename).split(';').next().unwrap_or("application/octet-stream").to_string()
+    }
+}
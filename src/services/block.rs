@@ -0,0 +1,80 @@
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::models::UserProfile;
+
+pub struct BlockService;
+
+impl BlockService {
+  pub async fn block(pool: &MySqlPool, blocker_id: i64, blocked_id: i64) -> Result<(), AppError> {
+    if blocker_id == blocked_id {
+      return Err(AppError::BadRequest("不能拉黑自己".to_string()))
+    }
+
+    // INSERT IGNORE：重复拉黑视为成功
+    sqlx::query(
+      "INSERT IGNORE INTO user_blocks (blocker_id, blocked_id) VALUES (?, ?)"
+    )
+    .bind(blocker_id)
+    .bind(blocked_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  pub async fn unblock(pool: &MySqlPool, blocker_id: i64, blocked_id: i64) -> Result<(), AppError> {
+    sqlx::query(
+      "DELETE FROM user_blocks WHERE blocker_id = ? AND blocked_id = ?"
+    )
+    .bind(blocker_id)
+    .bind(blocked_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  pub async fn list_blocked(pool: &MySqlPool, blocker_id: i64) -> Result<Vec<UserProfile>, AppError> {
+    sqlx::query_as::<_, UserProfile>(
+      "SELECT u.* FROM users u
+      JOIN user_blocks b ON b.blocked_id = u.id
+      WHERE b.blocker_id = ?
+      ORDER BY b.created_at DESC"
+    )
+    .bind(blocker_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  /// user_id 拉黑的所有用户 id，ChatServer 用来过滤广播
+  pub async fn blocked_ids(pool: &MySqlPool, user_id: i64) -> Result<Vec<i64>, AppError> {
+    sqlx::query_scalar::<_, i64>(
+      "SELECT blocked_id FROM user_blocks WHERE blocker_id = ?"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  /// 任意一方拉黑了另一方
+  pub async fn is_blocked_either(pool: &MySqlPool, a: i64, b: i64) -> Result<bool, AppError> {
+    let count = sqlx::query_scalar::<_, i64>(
+      "SELECT COUNT(*) FROM user_blocks
+      WHERE (blocker_id = ? AND blocked_id = ?) OR (blocker_id = ? AND blocked_id = ?)"
+    )
+    .bind(a)
+    .bind(b)
+    .bind(b)
+    .bind(a)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(count > 0)
+  }
+}
\ No newline at end of file
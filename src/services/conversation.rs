@@ -2,7 +2,9 @@ use serde::{ Serialize};
 use sqlx::MySqlPool;
 use sqlx::prelude::FromRow;
 use crate::error::AppError;
-use crate::models::{ MemberRole, ConversationType};
+use crate::config::DmPolicy;
+use crate::models::{ MemberRole, ConversationType, UpdateMemberSettings };
+use crate::services::{ BlockService, FriendShipService };
 
 #[derive(FromRow, Serialize)]
 pub struct ConversationRes {
@@ -14,16 +16,30 @@ pub struct ConversationRes {
   pub is_deleted: bool
 }
 
+/// 会话列表项：会话本身 + 当前用户的个人状态
+#[derive(FromRow, Serialize)]
+pub struct InboxItem {
+  #[sqlx(flatten)]
+  #[serde(flatten)]
+  pub conversation: ConversationRes,
+  pub is_pinned: bool,
+  pub is_archived: bool,
+  pub is_muted: bool,
+  pub last_message_at: chrono::DateTime<chrono::Utc>
+}
+
 pub struct ConversationServices;
 
 impl ConversationServices {
-  pub async fn create(pool: &MySqlPool, user_id: i64, name: Option<String>, member_ids: Vec<i64> ) -> Result<i64, AppError> {
+  pub async fn create(pool: &MySqlPool, user_id: i64, name: Option<String>, member_ids: Vec<i64>, dm_policy: DmPolicy) -> Result<i64, AppError> {
     let members_num = member_ids.len();
     let conv_type = if members_num > 1 { ConversationType::Group } else { ConversationType::Private };
 
     // 私聊查重：如果已存在两人之间的 Private 会话，直接返回已有的 id
     if members_num == 1 {
       let other_id = member_ids[0];
+      Self::check_dm_allowed(pool, user_id, other_id, dm_policy).await?;
+
       let result = sqlx::query_scalar::<_, i64>(
         "SELECT c.id
               FROM conversations c
@@ -83,6 +99,17 @@ impl ConversationServices {
     Ok(conversation_result.last_insert_id() as i64)
   }
 
+  /// 私聊权限：任意一方拉黑都不允许，FriendsOnly 策略下还必须是好友
+  pub async fn check_dm_allowed(pool: &MySqlPool, user_id: i64, other_id: i64, dm_policy: DmPolicy) -> Result<(), AppError> {
+    if BlockService::is_blocked_either(pool, user_id, other_id).await? {
+      return Err(AppError::Forbidden("无法与该用户私聊".to_string()));
+    }
+    if dm_policy == DmPolicy::FriendsOnly && !FriendShipService::is_friend(pool, user_id, other_id).await? {
+      return Err(AppError::Forbidden("只能与好友私聊".to_string()));
+    }
+    Ok(())
+  }
+
   /**
    * id: conversation_id
    * 查询需要用fetch_optional fetch_one
@@ -99,18 +126,104 @@ impl ConversationServices {
     .ok_or_else(|| AppError::NotFound("会话不存在".to_string()))
   }
 
-  pub async fn get_user_conversations(pool: &MySqlPool, user_id: i64) -> Result<Vec<ConversationRes>, AppError> {
+  /// 会话列表：隐藏的会话不返回，archived 决定查收件箱还是归档箱
+  /// 置顶的排最前，其余按最后一条消息时间倒序
+  pub async fn get_user_conversations(pool: &MySqlPool, user_id: i64, archived: bool, limit: i64, offset: i64) -> Result<Vec<InboxItem>, AppError> {
     sqlx::query_as(
-      "SELECT c.* FROM conversations c
+      "SELECT c.*, cm.is_pinned, cm.is_archived, cm.is_muted,
+              COALESCE((SELECT MAX(m.created_at) FROM messages m WHERE m.conversation_id = c.id), c.created_at) AS last_message_at
+      FROM conversations c
       JOIN conversation_member cm ON c.id = cm.conversation_id
-      WHERE cm.user_id = ?"
+      WHERE cm.user_id = ? AND cm.is_hidden = false AND cm.is_archived = ?
+      ORDER BY cm.is_pinned DESC, cm.pinned_at DESC, last_message_at DESC
+      LIMIT ? OFFSET ?"
     )
-    .bind(&user_id)
+    .bind(user_id)
+    .bind(archived)
+    .bind(limit)
+    .bind(offset)
     .fetch_all(pool)
     .await
     .map_err(|e| AppError::Internal(e.to_string()))
   }
 
+  pub async fn is_member(pool: &MySqlPool, conversation_id: i64, user_id: i64) -> Result<bool, AppError> {
+    let count = sqlx::query_scalar::<_, i64>(
+      "SELECT COUNT(*) FROM conversation_member WHERE conversation_id = ? AND user_id = ?"
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(count > 0)
+  }
+
+  /// 修改当前用户对会话的置顶/归档/隐藏/免打扰状态，只影响自己
+  pub async fn update_settings(pool: &MySqlPool, conversation_id: i64, user_id: i64, data: UpdateMemberSettings) -> Result<(), AppError> {
+    if !Self::is_member(pool, conversation_id, user_id).await? {
+      return Err(AppError::NotFound("会话不存在".to_string()));
+    }
+
+    sqlx::query(
+      "UPDATE conversation_member SET
+        pinned_at = CASE WHEN ? IS NULL THEN pinned_at WHEN ? THEN COALESCE(pinned_at, NOW()) ELSE NULL END,
+        is_pinned = COALESCE(?, is_pinned),
+        is_archived = COALESCE(?, is_archived),
+        is_hidden = COALESCE(?, is_hidden),
+        is_muted = COALESCE(?, is_muted)
+      WHERE conversation_id = ? AND user_id = ?"
+    )
+    .bind(data.pinned)
+    .bind(data.pinned)
+    .bind(data.pinned)
+    .bind(data.archived)
+    .bind(data.hidden)
+    .bind(data.muted)
+    .bind(conversation_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 清空聊天记录：只记录清空时间，历史消息查询时过滤掉之前的消息
+  pub async fn clear_history(pool: &MySqlPool, conversation_id: i64, user_id: i64) -> Result<(), AppError> {
+    if !Self::is_member(pool, conversation_id, user_id).await? {
+      return Err(AppError::NotFound("会话不存在".to_string()));
+    }
+
+    sqlx::query(
+      "UPDATE conversation_member SET cleared_at = NOW() WHERE conversation_id = ? AND user_id = ?"
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 新消息到达：隐藏的会话重新出现，归档的会话除非免打扰否则移回收件箱
+  pub async fn on_new_message(pool: &MySqlPool, conversation_id: i64) -> Result<(), AppError> {
+    sqlx::query(
+      "UPDATE conversation_member SET
+        is_hidden = false,
+        is_archived = is_archived AND is_muted
+      WHERE conversation_id = ?"
+    )
+    .bind(conversation_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
   pub async fn add_member(pool: &MySqlPool, user_id: i64, conversation_id: i64, role: MemberRole) -> Result<(), AppError> {
     sqlx::query(
       "INSERT INTO conversation_member (conversation_id, user_id, role) VALUES (?, ?, ?)"
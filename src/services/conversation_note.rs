@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use sqlx::{MySqlPool, QueryBuilder};
+
+use crate::error::AppError;
+use crate::models::{NoteCard, SharedNote};
+use crate::services::{ConversationServices, NoteService};
+
+/// 卡片需要的笔记和作者字段，回收站里的笔记不显示
+const NOTE_CARD: &str =
+  "SELECT n.id, n.title, n.excerpt, n.user_id AS owner_id,
+    COALESCE(u.display_name, u.username) AS owner_name, u.avatar_url AS owner_avatar_url";
+
+/// 把笔记以卡片消息的形式分享到会话，分享期间会话成员都能读取这篇笔记
+pub struct ConversationNoteService;
+
+impl ConversationNoteService {
+  /// 只有作者本人可以分享；返回消息的兜底文字和卡片
+  pub async fn share(pool: &MySqlPool, conversation_id: i64, user_id: i64, note_id: i64) -> Result<(String, NoteCard), AppError> {
+    if !ConversationServices::is_member(pool, conversation_id, user_id).await? {
+      return Err(AppError::NotFound("会话不存在".to_string()));
+    }
+    let note = NoteService::find_owned(pool, note_id, user_id).await?;
+    // 不认识卡片的旧客户端显示这段文字
+    let content = format!("分享了笔记《{}》", note.title);
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+    sqlx::query("INSERT IGNORE INTO conversation_notes (conversation_id, note_id, shared_by) VALUES (?, ?, ?)")
+      .bind(conversation_id)
+      .bind(note_id)
+      .bind(user_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("INSERT INTO messages (conversation_id, sender_id, content, msg_type, note_id) VALUES (?, ?, ?, 'note', ?)")
+      .bind(conversation_id)
+      .bind(user_id)
+      .bind(&content)
+      .bind(note_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    ConversationServices::on_new_message(pool, conversation_id).await?;
+    let card = Self::cards(pool, conversation_id, &[note_id]).await?
+      .remove(&note_id)
+      .ok_or_else(|| AppError::Internal("笔记卡片查询失败".to_string()))?;
+    Ok((content, card))
+  }
+
+  /// 取消分享，成员立即失去读取权限，历史消息里的卡片显示为不可用
+  pub async fn unshare(pool: &MySqlPool, conversation_id: i64, user_id: i64, note_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query(
+      "DELETE FROM conversation_notes WHERE conversation_id = ? AND note_id = ?
+        AND note_id IN (SELECT id FROM notes WHERE user_id = ?)"
+    )
+      .bind(conversation_id)
+      .bind(note_id)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::NotFound("笔记未分享到该会话".to_string()));
+    }
+    Ok(())
+  }
+
+  /// 会话里分享过、仍在分享中的笔记，最近分享的在前
+  pub async fn list(pool: &MySqlPool, conversation_id: i64, user_id: i64) -> Result<Vec<SharedNote>, AppError> {
+    if !ConversationServices::is_member(pool, conversation_id, user_id).await? {
+      return Err(AppError::NotFound("会话不存在".to_string()));
+    }
+
+    sqlx::query_as::<_, SharedNote>(&format!(
+      "{}, cn.shared_by, cn.created_at AS shared_at
+      FROM conversation_notes cn
+      JOIN notes n ON n.id = cn.note_id AND n.deleted_at IS NULL
+      JOIN users u ON u.id = n.user_id
+      WHERE cn.conversation_id = ?
+      ORDER BY cn.created_at DESC",
+      NOTE_CARD
+    ))
+      .bind(conversation_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  /// 批量查询仍分享在该会话里的笔记卡片，聊天记录用
+  pub async fn cards(pool: &MySqlPool, conversation_id: i64, note_ids: &[i64]) -> Result<HashMap<i64, NoteCard>, AppError> {
+    if note_ids.is_empty() {
+      return Ok(HashMap::new());
+    }
+
+    let mut query = QueryBuilder::new(format!(
+      "{} FROM conversation_notes cn
+      JOIN notes n ON n.id = cn.note_id AND n.deleted_at IS NULL
+      JOIN users u ON u.id = n.user_id
+      WHERE cn.conversation_id = ",
+      NOTE_CARD
+    ));
+    query.push_bind(conversation_id).push(" AND cn.note_id IN (");
+    let mut separated = query.separated(", ");
+    for note_id in note_ids {
+      separated.push_bind(*note_id);
+    }
+    separated.push_unseparated(")");
+
+    let cards = query.build_query_as::<NoteCard>()
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(cards.into_iter().map(|card| (card.id, card)).collect())
+  }
+}
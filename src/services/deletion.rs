@@ -0,0 +1,169 @@
+use bcrypt::{hash, DEFAULT_COST};
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::services::{ApiTokenService, UserService};
+use crate::utils::{Storage, TokenUtil};
+
+/// 注销账号：申请后进入宽限期，到期由后台任务清理
+pub struct AccountDeletionService;
+
+impl AccountDeletionService {
+  /// 登记注销并让所有 token 失效，返回计划删除时间；调用方负责断开 WebSocket
+  /// password 为 None 表示不校验 (只能通过 SSO 登录的部署)
+  pub async fn request(pool: &MySqlPool, user_id: i64, password: Option<&str>, grace_days: i64) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    if user.deletion_scheduled_at.is_some() {
+      return Err(AppError::BadRequest("已经申请过注销".to_string()));
+    }
+    if let Some(password) = password && !UserService::verify_password(password, &user.password_hash)? {
+      return Err(AppError::Unauthorized("当前密码错误".to_string()));
+    }
+
+    sqlx::query(
+      "UPDATE users SET deletion_scheduled_at = NOW() + INTERVAL ? DAY, token_version = token_version + 1
+      WHERE id = ? AND deletion_scheduled_at IS NULL"
+    )
+      .bind(grace_days)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    ApiTokenService::revoke_all(pool, user_id).await?;
+
+    UserService::find_by_id(pool, user_id).await?
+      .deletion_scheduled_at
+      .ok_or_else(|| AppError::Internal("deletion_scheduled_at not set".to_string()))
+  }
+
+  /// 宽限期内重新登录后可以撤销
+  pub async fn cancel(pool: &MySqlPool, user_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query(
+      "UPDATE users SET deletion_scheduled_at = NULL WHERE id = ? AND deletion_scheduled_at IS NOT NULL AND deleted_at IS NULL"
+    )
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::BadRequest("没有待处理的注销申请".to_string()));
+    }
+    Ok(())
+  }
+
+  /// 清理所有到期的账号，返回清理数量；单个账号失败不影响其他账号
+  pub async fn purge_due(pool: &MySqlPool, storage: &Storage) -> Result<usize, AppError> {
+    let ids = sqlx::query_scalar::<_, i64>(
+      "SELECT id FROM users WHERE deletion_scheduled_at <= NOW() AND deleted_at IS NULL"
+    )
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let mut purged = 0;
+    for id in ids {
+      match Self::purge(pool, storage, id).await {
+        Ok(()) => purged += 1,
+        Err(e) => tracing::error!("账号 {} 清理失败：{}", id, e),
+      }
+    }
+    Ok(purged)
+  }
+
+  /// 删除笔记、文件夹、标签、好友关系、会话成员身份和各种凭证；users 行匿名化后保留，
+  /// 聊天记录仍然指向它，显示为"已注销用户"
+  async fn purge(pool: &MySqlPool, storage: &Storage, user_id: i64) -> Result<(), AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    // (语句, user_id 绑定次数)
+    let statements: [(&str, usize); 21] = [
+      ("DELETE FROM note_shares WHERE user_id = ? OR note_id IN (SELECT id FROM notes WHERE user_id = ?)", 2),
+      ("DELETE FROM note_publications WHERE note_id IN (SELECT id FROM notes WHERE user_id = ?)", 1),
+      ("DELETE FROM conversation_notes WHERE shared_by = ? OR note_id IN (SELECT id FROM notes WHERE user_id = ?)", 2),
+      ("DELETE FROM note_links WHERE source_id IN (SELECT id FROM notes WHERE user_id = ?) OR target_id IN (SELECT id FROM notes WHERE user_id = ?)", 2),
+      ("DELETE FROM note_tags WHERE note_id IN (SELECT id FROM notes WHERE user_id = ?) OR tag_id IN (SELECT id FROM tags WHERE user_id = ?)", 2),
+      ("DELETE FROM tags WHERE user_id = ?", 1),
+      ("DELETE FROM notes WHERE user_id = ?", 1),
+      ("DELETE FROM folder_shares WHERE user_id = ? OR folder_id IN (SELECT id FROM folders WHERE user_id = ?)", 2),
+      ("DELETE FROM folders WHERE user_id = ?", 1),
+      ("DELETE FROM note_templates WHERE user_id = ?", 1),
+      ("DELETE FROM friendships WHERE requester_id = ? OR receiver_id = ?", 2),
+      ("DELETE FROM friend_label_members WHERE friend_id = ? OR label_id IN (SELECT id FROM friend_labels WHERE user_id = ?)", 2),
+      ("DELETE FROM friend_labels WHERE user_id = ?", 1),
+      ("DELETE FROM user_blocks WHERE blocker_id = ? OR blocked_id = ?", 2),
+      ("DELETE FROM conversation_member WHERE user_id = ?", 1),
+      ("DELETE FROM user_tokens WHERE user_id = ?", 1),
+      ("DELETE FROM user_recovery_codes WHERE user_id = ?", 1),
+      ("DELETE FROM user_identities WHERE user_id = ?", 1),
+      ("DELETE FROM api_tokens WHERE user_id = ?", 1),
+      ("DELETE FROM oidc_states WHERE user_id = ?", 1),
+      ("DELETE FROM login_attempts WHERE user_id = ?", 1),
+    ];
+    for (statement, binds) in statements {
+      let mut query = sqlx::query(statement);
+      for _ in 0..binds {
+        query = query.bind(user_id);
+      }
+      query
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+
+    // 随机密码的 bcrypt 哈希：格式合法，登录时按密码错误处理而不是 500
+    let password = hash(TokenUtil::generate(), DEFAULT_COST).map_err(|e| AppError::Internal(e.to_string()))?;
+    sqlx::query(
+      "UPDATE users SET
+        username = ?, password = ?, email = NULL, phone = NULL,
+        display_name = '已注销用户', avatar_url = NULL, bio = NULL, status_text = NULL,
+        totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL,
+        disabled_at = NOW(), disabled_reason = 'deleted', deleted_at = NOW(),
+        token_version = token_version + 1
+      WHERE id = ?"
+    )
+      .bind(Self::anonymized_username(user_id))
+      .bind(&password)
+      .bind(user_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if let Some(key) = user.avatar_url.as_deref().and_then(|url| url.strip_prefix("/files/")) {
+      storage.delete(key).await?;
+    }
+    tracing::info!("账号 {} 已注销", user_id);
+    Ok(())
+  }
+
+  /// 注销后的用户名带 `#`，注册和 SSO 生成的用户名都不会含这个字符，不会撞上别人的名字
+  fn anonymized_username(user_id: i64) -> String {
+    format!("#deleted-{}", user_id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use validator::Validate;
+  use crate::models::RegisterRequest;
+  use super::AccountDeletionService;
+
+  fn register(username: &str) -> RegisterRequest {
+    RegisterRequest { username: username.to_string(), password: "password123".to_string(), email: None }
+  }
+
+  #[test]
+  fn anonymized_username_cannot_be_registered() {
+    assert!(register(&AccountDeletionService::anonymized_username(7)).validate().is_err());
+  }
+
+  #[test]
+  fn anonymized_username_does_not_collide_with_taken_name() {
+    // 别人注册了 deleted-7，清理 7 号账号时不能撞上 uk_users_username_lower
+    assert!(register("deleted-7").validate().is_ok());
+    assert_ne!(AccountDeletionService::anonymized_username(7).to_lowercase(), "deleted-7");
+  }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+
+use actix_web::web;
+use serde::Serialize;
+use sqlx::{FromRow, MySqlPool};
+use zip::write::SimpleFileOptions;
+
+use crate::error::AppError;
+use crate::models::{ContentFormat, Folder, Note, NoteExportFormat};
+use crate::services::conversation::InboxItem;
+use crate::utils::{Markdown, Pdf};
+use crate::services::{BlockService, FolderService, FriendLabelService, FriendShipService, NoteService, SsoService, UserService};
+
+#[derive(Debug, FromRow, Serialize)]
+struct ExportedMessage {
+  id: i64,
+  conversation_id: i64,
+  content: String,
+  msg_type: String,
+  created_at: chrono::DateTime<chrono::Utc>
+}
+
+/// 个人数据导出 (GDPR) 和笔记导出
+pub struct ExportService;
+
+impl ExportService {
+  /// zip 包：资料、好友、会话、自己发的消息 (JSON) + 每篇笔记一个 Markdown 文件
+  pub async fn export_user(pool: &MySqlPool, user_id: i64) -> Result<Vec<u8>, AppError> {
+    let profile = UserService::find_by_id(pool, user_id).await?;
+    let identities = SsoService::list_identities(pool, user_id).await?;
+    let notes = NoteService::find_all(pool, user_id).await?;
+    let folders = FolderService::find_all(pool, user_id).await?;
+    let trash = NoteService::find_trash(pool, user_id).await?;
+    let friends = FriendShipService::list_friends(pool, user_id, None).await?;
+    let incoming = FriendShipService::list_pending(pool, user_id).await?;
+    let outgoing = FriendShipService::list_outgoing(pool, user_id).await?;
+    let labels = FriendLabelService::list(pool, user_id).await?;
+    let blocked = BlockService::list_blocked(pool, user_id).await?;
+
+    // 包括已归档和已隐藏的会话
+    let conversations = sqlx::query_as::<_, InboxItem>(
+      "SELECT c.*, cm.is_pinned, cm.is_archived, cm.is_muted,
+              COALESCE((SELECT MAX(m.created_at) FROM messages m WHERE m.conversation_id = c.id), c.created_at) AS last_message_at
+      FROM conversations c
+      JOIN conversation_member cm ON c.id = cm.conversation_id
+      WHERE cm.user_id = ?
+      ORDER BY c.id"
+    )
+      .bind(user_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let messages = sqlx::query_as::<_, ExportedMessage>(
+      "SELECT id, conversation_id, content, msg_type, created_at FROM messages WHERE sender_id = ? ORDER BY id"
+    )
+      .bind(user_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let mut files: Vec<(String, Vec<u8>)> = vec![
+      ("profile.json".to_string(), Self::json(&serde_json::json!({ "user": profile, "identities": identities }))?),
+      ("friends.json".to_string(), Self::json(&serde_json::json!({
+        "friends": friends,
+        "incoming_requests": incoming,
+        "outgoing_requests": outgoing,
+        "labels": labels,
+        "blocked": blocked
+      }))?),
+      ("conversations.json".to_string(), Self::json(&conversations)?),
+      ("messages.json".to_string(), Self::json(&messages)?),
+      ("notes.json".to_string(), Self::json(&notes)?),
+      ("folders.json".to_string(), Self::json(&folders)?),
+      ("trash.json".to_string(), Self::json(&trash)?),
+    ];
+    for note in &notes {
+      files.push((format!("notes/{}.md", note.file_stem()), note.to_markdown().into_bytes()));
+    }
+
+    web::block(move || Self::zip(files))
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+  }
+
+  /// 单篇笔记导出，返回 (Content-Type, 文件名, 内容)；PDF 只保留文字
+  pub fn export_note(note: &Note, format: NoteExportFormat) -> (&'static str, String, Vec<u8>) {
+    let stem = note.file_stem();
+    match format {
+      NoteExportFormat::Md => ("text/markdown; charset=utf-8", format!("{}.md", stem), note.to_markdown().into_bytes()),
+      NoteExportFormat::Html => {
+        let title = Markdown::escape(&note.title);
+        let html = format!(
+          "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n{}\n</body>\n</html>\n",
+          title, title, note.render_html()
+        );
+        ("text/html; charset=utf-8", format!("{}.html", stem), html.into_bytes())
+      }
+      NoteExportFormat::Pdf => {
+        let content = note.content.as_deref().unwrap_or("");
+        let body = match note.content_format {
+          ContentFormat::Markdown => Markdown::plain_text(content),
+          ContentFormat::Plain => content.to_string(),
+        };
+        ("application/pdf", format!("{}.pdf", stem), Pdf::text_document(&note.title, &body))
+      }
+    }
+  }
+
+  /// 批量导出为 Markdown zip 包，目录结构和文件夹一致；folder_id 为 None 时导出全部笔记
+  pub async fn export_notes(pool: &MySqlPool, user_id: i64, folder_id: Option<i64>) -> Result<Vec<u8>, AppError> {
+    if let Some(folder_id) = folder_id {
+      FolderService::find_owned(pool, folder_id, user_id).await?;
+    }
+    let folders: HashMap<i64, Folder> = FolderService::find_all(pool, user_id).await?
+      .into_iter()
+      .map(|folder| (folder.id, folder))
+      .collect();
+
+    let mut files = Vec::new();
+    for note in NoteService::find_all(pool, user_id).await? {
+      // 从笔记所在文件夹往上拼路径，到导出的根文件夹为止，不在范围内的跳过
+      let mut dirs = Vec::new();
+      let mut inside = folder_id.is_none();
+      let mut current = note.folder_id;
+      while let Some(id) = current {
+        if Some(id) == folder_id {
+          inside = true;
+          break;
+        }
+        let Some(folder) = folders.get(&id) else { break };
+        dirs.push(folder.dir_name());
+        current = folder.parent_id;
+      }
+      if !inside {
+        continue;
+      }
+
+      dirs.reverse();
+      dirs.push(format!("{}.md", note.file_stem()));
+      files.push((dirs.join("/"), note.to_markdown().into_bytes()));
+    }
+
+    web::block(move || Self::zip(files))
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+  }
+
+  pub fn zip(files: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, AppError> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, content) in files {
+      writer.start_file(name, options).map_err(|e| AppError::Internal(e.to_string()))?;
+      writer.write_all(&content).map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+
+    writer.finish()
+      .map(|cursor| cursor.into_inner())
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  fn json<T: Serialize>(value: &T) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec_pretty(value).map_err(|e| AppError::Internal(e.to_string()))
+  }
+}
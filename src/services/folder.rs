@@ -0,0 +1,239 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::error::AppError;
+use crate::models::{CreateFolder, Folder, FolderContents};
+use crate::services::{FriendShipService, NoteService};
+
+pub struct FolderService;
+
+impl FolderService {
+    /// 自己的全部文件夹 (扁平列表)，客户端按 parent_id 组装成树
+    pub async fn find_all(pool: &MySqlPool, user_id: i64) -> Result<Vec<Folder>, AppError> {
+        sqlx::query_as::<_, Folder>("SELECT * FROM folders WHERE user_id = ? ORDER BY parent_id, name")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn find_by_id(pool: &MySqlPool, id: i64) -> Result<Folder, AppError> {
+        sqlx::query_as::<_, Folder>("SELECT * FROM folders WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound(format!("Folder {} not found", id)))
+    }
+
+    pub async fn find_owned(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Folder, AppError> {
+        let folder = Self::find_by_id(pool, id).await?;
+        if folder.user_id != user_id {
+            return Err(AppError::NotFound(format!("Folder {} not found", id)));
+        }
+        Ok(folder)
+    }
+
+    /// 作者本人，或者该文件夹 / 任意上级文件夹被共享给了 user_id
+    pub async fn find_accessible(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Folder, AppError> {
+        let folder = Self::find_by_id(pool, id).await?;
+        if folder.user_id != user_id && !Self::is_shared_with(pool, id, user_id).await? {
+            return Err(AppError::NotFound(format!("Folder {} not found", id)));
+        }
+        Ok(folder)
+    }
+
+    /// 沿 parent_id 向上查找，任意一级被共享给 user_id 即可见
+    pub async fn is_shared_with(pool: &MySqlPool, id: i64, user_id: i64) -> Result<bool, AppError> {
+        let shared = sqlx::query_scalar::<_, i64>(
+            "WITH RECURSIVE ancestors (id, parent_id) AS (
+                SELECT id, parent_id FROM folders WHERE id = ?
+                UNION ALL
+                SELECT f.id, f.parent_id FROM folders f JOIN ancestors a ON f.id = a.parent_id
+             )
+             SELECT EXISTS (SELECT 1 FROM folder_shares s JOIN ancestors a ON s.folder_id = a.id WHERE s.user_id = ?)"
+        )
+            .bind(id)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(shared > 0)
+    }
+
+    pub async fn create(pool: &MySqlPool, data: CreateFolder, user_id: i64) -> Result<Folder, AppError> {
+        if let Some(parent_id) = data.parent_id {
+            Self::find_owned(pool, parent_id, user_id).await?;
+        }
+
+        let result = sqlx::query("INSERT INTO folders (user_id, parent_id, name) VALUES (?, ?, ?)")
+            .bind(user_id)
+            .bind(data.parent_id)
+            .bind(&data.name)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_by_id(pool, result.last_insert_id() as i64).await
+    }
+
+    pub async fn rename(pool: &MySqlPool, id: i64, user_id: i64, name: &str) -> Result<Folder, AppError> {
+        Self::find_owned(pool, id, user_id).await?;
+
+        sqlx::query("UPDATE folders SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_by_id(pool, id).await
+    }
+
+    /// 移动到另一个文件夹下 (None 为根目录)，不能移到自己或自己的子文件夹里
+    /// 检查和更新在同一个事务里并锁住该用户的全部文件夹，A→B、B→A 并发移动时不会形成环
+    pub async fn move_to(pool: &MySqlPool, id: i64, user_id: i64, parent_id: Option<i64>) -> Result<Folder, AppError> {
+        Self::find_owned(pool, id, user_id).await?;
+        let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("SELECT id FROM folders WHERE user_id = ? FOR UPDATE")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if let Some(parent_id) = parent_id {
+            Self::find_owned(pool, parent_id, user_id).await?;
+            if Self::ancestor_ids(&mut tx, parent_id).await?.contains(&id) {
+                return Err(AppError::BadRequest("不能移动到自己的子文件夹中".to_string()));
+            }
+        }
+
+        sqlx::query("UPDATE folders SET parent_id = ? WHERE id = ?")
+            .bind(parent_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_by_id(pool, id).await
+    }
+
+    /// 删除文件夹本身，里面的笔记和子文件夹移到上一级，不会丢内容
+    pub async fn delete(pool: &MySqlPool, id: i64, user_id: i64) -> Result<(), AppError> {
+        let folder = Self::find_owned(pool, id, user_id).await?;
+        let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("UPDATE notes SET folder_id = ? WHERE folder_id = ?")
+            .bind(folder.parent_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("UPDATE folders SET parent_id = ? WHERE parent_id = ?")
+            .bind(folder.parent_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM folder_shares WHERE folder_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM folders WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 文件夹的直接内容；id 为 None 时返回自己根目录下的内容
+    pub async fn contents(pool: &MySqlPool, id: Option<i64>, user_id: i64) -> Result<FolderContents, AppError> {
+        let folder = match id {
+            Some(id) => Some(Self::find_accessible(pool, id, user_id).await?),
+            None => None,
+        };
+
+        let folders = match &folder {
+            Some(folder) => sqlx::query_as::<_, Folder>("SELECT * FROM folders WHERE parent_id = ? ORDER BY name")
+                .bind(folder.id)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, Folder>("SELECT * FROM folders WHERE user_id = ? AND parent_id IS NULL ORDER BY name")
+                .bind(user_id)
+                .fetch_all(pool)
+                .await,
+        }
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let notes = NoteService::find_in_folder(pool, folder.as_ref().map(|f| f.id), user_id).await?;
+        Ok(FolderContents { folder, folders, notes })
+    }
+
+    /// 只能共享给好友，规则和笔记共享相同
+    pub async fn share(pool: &MySqlPool, id: i64, owner_id: i64, user_ids: &[i64]) -> Result<(), AppError> {
+        Self::find_owned(pool, id, owner_id).await?;
+        FriendShipService::ensure_share_targets(pool, owner_id, user_ids).await?;
+
+        for user_id in user_ids.iter().filter(|u| **u != owner_id) {
+            sqlx::query("INSERT IGNORE INTO folder_shares (folder_id, user_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn unshare(pool: &MySqlPool, id: i64, owner_id: i64, user_id: i64) -> Result<(), AppError> {
+        Self::find_owned(pool, id, owner_id).await?;
+
+        sqlx::query("DELETE FROM folder_shares WHERE folder_id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 别人直接共享给我的文件夹 (子文件夹通过 contents 浏览)
+    pub async fn find_shared_with(pool: &MySqlPool, user_id: i64) -> Result<Vec<Folder>, AppError> {
+        sqlx::query_as::<_, Folder>(
+            "SELECT f.* FROM folders f
+             JOIN folder_shares s ON s.folder_id = f.id
+             WHERE s.user_id = ?
+             ORDER BY s.created_at DESC"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// id 自身及所有上级文件夹的 id
+    async fn ancestor_ids(conn: &mut MySqlConnection, id: i64) -> Result<Vec<i64>, AppError> {
+        sqlx::query_scalar::<_, i64>(
+            "WITH RECURSIVE ancestors (id, parent_id) AS (
+                SELECT id, parent_id FROM folders WHERE id = ?
+                UNION ALL
+                SELECT f.id, f.parent_id FROM folders f JOIN ancestors a ON f.id = a.parent_id
+             )
+             SELECT id FROM ancestors"
+        )
+            .bind(id)
+            .fetch_all(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
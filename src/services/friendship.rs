@@ -1,62 +1,134 @@
+use serde::Serialize;
 use sqlx::MySqlPool;
+use sqlx::prelude::FromRow;
 
-use crate::{error::AppError, models::FriendShip};
+use crate::{error::AppError, models::{FriendShip, PublicUser, User, UserProfile}};
 use crate::models::FriendShipStatus;
+use crate::services::{BlockService, UserService};
+
+/// 好友推荐：候选用户 + 共同好友数 + 共同群聊数
+#[derive(FromRow, Serialize)]
+pub struct FriendSuggestion {
+  #[sqlx(flatten)]
+  #[serde(flatten)]
+  pub user: UserProfile,
+  pub mutual_friends: i64,
+  pub shared_groups: i64
+}
+
+/// 好友请求 + 对方的资料 (收到的请求是发起方，发出的请求是接收方)
+#[derive(Serialize)]
+pub struct FriendRequestRes {
+  #[serde(flatten)]
+  pub friendship: FriendShip,
+  pub user: Option<UserProfile>
+}
 
 pub struct FriendShipService;
 
 impl FriendShipService {
-  pub async fn send_request(pool: &MySqlPool, requester_id: i64, receiver_id: i64) -> Result<bool, AppError> {
+  /// 发送好友请求，返回处理后的记录
+  /// 两人之间只保留一条记录：对方已向我发过请求时直接互相接受，被拒绝过的记录复用并改为我发起
+  pub async fn send_request(pool: &MySqlPool, requester_id: i64, receiver_id: i64, message: Option<String>) -> Result<FriendShip, AppError> {
     if requester_id == receiver_id {
       return Err(AppError::Unauthorized("不能添加自己为好友".to_string()))
     }
-    let result = sqlx::query_as::<_, FriendShip>(
-      "SELECT * FROM friendships WHERE requester_id = ? AND receiver_id = ?"
+    if BlockService::is_blocked_either(pool, requester_id, receiver_id).await? {
+      return Err(AppError::Forbidden("无法添加该用户为好友".to_string()))
+    }
+    let records = sqlx::query_as::<_, FriendShip>(
+      "SELECT * FROM friendships WHERE (requester_id = ? AND receiver_id = ?) OR (requester_id = ? AND receiver_id = ?)"
     )
     .bind(requester_id)
     .bind(receiver_id)
-    .fetch_optional(pool)
+    .bind(receiver_id)
+    .bind(requester_id)
+    .fetch_all(pool)
     .await
     .map_err(|e| AppError::Internal(e.to_string()))?;
 
-    match result {
+    if records.iter().any(|r| matches!(r.status, FriendShipStatus::Accepted)) {
+      return Err(AppError::Unauthorized("对方已是您的好友".to_string()))
+    }
+
+    // 对方已经向我发过请求：双向请求视为互相同意
+    if let Some(reverse) = records.iter().find(|r| r.requester_id == receiver_id && matches!(r.status, FriendShipStatus::Pending)) {
+      return Self::accept(pool, reverse.id, requester_id).await
+    }
+
+    if records.iter().any(|r| matches!(r.status, FriendShipStatus::Pending)) {
+      return Err(AppError::Unauthorized("不能重复添加".to_string()))
+    }
+
+    let id = match records.first() {
       Some(record) => {
-        match record.status {
-          FriendShipStatus::Accepted => {
-            return Err(AppError::Unauthorized("对方已是您的好友".to_string()))
-          },
-          FriendShipStatus::Pending => {
-            return Err(AppError::Unauthorized("不能重复添加".to_string()))
-          }
-          FriendShipStatus::Rejected => {
-            sqlx::query(
-              "UPDATE friendships SET status = 'pending' WHERE id = ? AND requester_id = ?"
-            )
-            .bind(record.id)
-            .bind(requester_id)
-            .execute(pool)
-            .await
-            .map_err(|e| AppError::Internal(e.to_string()))?;
-          }
-        }
-      },
-      None => {
         sqlx::query(
-          "INSERT INTO friendships (requester_id, receiver_id, status) VALUE (?, ?, 'pending')"
+          "UPDATE friendships SET requester_id = ?, receiver_id = ?, status = 'pending', message = ? WHERE id = ?"
         )
         .bind(requester_id)
         .bind(receiver_id)
+        .bind(&message)
+        .bind(record.id)
         .execute(pool)
         .await
         .map_err(|e| AppError::Internal(e.to_string()))?;
+        record.id
+      },
+      None => {
+        let result = sqlx::query(
+          "INSERT INTO friendships (requester_id, receiver_id, status, message) VALUE (?, ?, 'pending', ?)"
+        )
+        .bind(requester_id)
+        .bind(receiver_id)
+        .bind(&message)
+        .execute(pool)
+        .await;
+        match result {
+          Ok(result) => result.last_insert_id() as i64,
+          // 对方同时向我发了请求，(user_low, user_high) 唯一键挡住了这一条
+          Err(e) if e.as_database_error().is_some_and(|d| d.is_unique_violation()) => {
+            return Self::resolve_concurrent(pool, requester_id, receiver_id).await
+          },
+          Err(e) => return Err(AppError::Internal(e.to_string()))
+        }
       }
+    };
+    Self::find_by_id(pool, id).await
+  }
+
+  /// 插入时撞上了并发写入的记录：是对方发来的请求就直接接受
+  async fn resolve_concurrent(pool: &MySqlPool, requester_id: i64, receiver_id: i64) -> Result<FriendShip, AppError> {
+    let record = sqlx::query_as::<_, FriendShip>(
+      "SELECT * FROM friendships WHERE user_low = LEAST(?, ?) AND user_high = GREATEST(?, ?)"
+    )
+    .bind(requester_id)
+    .bind(receiver_id)
+    .bind(requester_id)
+    .bind(receiver_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .ok_or_else(|| AppError::Internal("好友记录并发写入后查询失败".to_string()))?;
+
+    match record.status {
+      FriendShipStatus::Accepted => Err(AppError::Unauthorized("对方已是您的好友".to_string())),
+      FriendShipStatus::Pending if record.requester_id == receiver_id => Self::accept(pool, record.id, requester_id).await,
+      _ => Err(AppError::Unauthorized("不能重复添加".to_string()))
     }
-    Ok(true)
   }
 
-  pub async fn accept(pool: &MySqlPool, friendship_id: i64, user_id: i64) -> Result<bool, AppError> {
+  pub async fn find_by_id(pool: &MySqlPool, friendship_id: i64) -> Result<FriendShip, AppError> {
+    sqlx::query_as::<_, FriendShip>("SELECT * FROM friendships WHERE id = ?")
+      .bind(friendship_id)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+      .ok_or_else(|| AppError::NotFound("好友请求不存在".to_string()))
+  }
+
+  pub async fn accept(pool: &MySqlPool, friendship_id: i64, user_id: i64) -> Result<FriendShip, AppError> {
       let result = sqlx::query(
-          "UPDATE friendships SET status = 'accepted' WHERE id = ? AND receiver_id = ?" 
+          "UPDATE friendships SET status = 'accepted' WHERE id = ? AND receiver_id = ? AND status = 'pending'" 
         )
         .bind(friendship_id)
         .bind(user_id)
@@ -67,7 +139,57 @@ impl FriendShipService {
     if result.rows_affected() == 0 {
       return Err(AppError::Unauthorized("只有接收方可以接收好友请求".to_string()))
     }
-    return Ok(true)
+    Self::find_by_id(pool, friendship_id).await
+  }
+
+  /// 撤回自己发出、对方还未处理的请求
+  pub async fn cancel(pool: &MySqlPool, friendship_id: i64, requester_id: i64) -> Result<FriendShip, AppError> {
+    let record = Self::find_by_id(pool, friendship_id).await?;
+    if record.requester_id != requester_id || !matches!(record.status, FriendShipStatus::Pending) {
+      return Err(AppError::NotFound("好友请求不存在".to_string()))
+    }
+
+    sqlx::query("DELETE FROM friendships WHERE id = ?")
+      .bind(friendship_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(record)
+  }
+
+  /// 删除好友，双方任意一方都可以操作
+  pub async fn remove_friend(pool: &MySqlPool, user_id: i64, friend_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query(
+      "DELETE FROM friendships WHERE status = 'accepted' AND ((requester_id = ? AND receiver_id = ?) OR (requester_id = ? AND receiver_id = ?))"
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .bind(friend_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::NotFound("对方不是您的好友".to_string()))
+    }
+
+    // 双方的好友标签里都移除对方
+    sqlx::query(
+      "DELETE m FROM friend_label_members m
+      JOIN friend_labels l ON l.id = m.label_id
+      WHERE (l.user_id = ? AND m.friend_id = ?) OR (l.user_id = ? AND m.friend_id = ?)"
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .bind(friend_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
   }
 
   pub async fn reject(pool: &MySqlPool, friendship_id: i64, receiver_id: i64) -> Result<bool, AppError> {
@@ -84,10 +206,8 @@ impl FriendShipService {
   }
 
   // 查询所有 receiver_id = user_id 且 status = 'pending' 的记录
-  // 返回 Result<Vec<FriendShip>, AppError>
-  pub async fn list_pending(pool: &MySqlPool, user_id: i64) -> Result<Vec<FriendShip>, AppError> {
-    // todo!()
-    let result = sqlx::query_as::<_, FriendShip>(
+  pub async fn list_pending(pool: &MySqlPool, user_id: i64) -> Result<Vec<FriendRequestRes>, AppError> {
+    let records = sqlx::query_as::<_, FriendShip>(
       "SELECT * FROM friendships WHERE receiver_id = ? AND status = 'pending'"
     )
     .bind(user_id)
@@ -95,28 +215,61 @@ impl FriendShipService {
     .await
     .map_err(|e| AppError::Internal(e.to_string()))?;
 
-    Ok(result)
+    Self::with_profiles(pool, records, |r| r.requester_id).await
   }
 
-  pub async fn list_friends(pool: &MySqlPool, user_id: i64) -> Result<Vec<crate::models::User>, AppError> {
-    sqlx::query_as::<_, crate::models::User>(
+  // 我发出的、对方还未处理的请求
+  pub async fn list_outgoing(pool: &MySqlPool, user_id: i64) -> Result<Vec<FriendRequestRes>, AppError> {
+    let records = sqlx::query_as::<_, FriendShip>(
+      "SELECT * FROM friendships WHERE requester_id = ? AND status = 'pending' ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::with_profiles(pool, records, |r| r.receiver_id).await
+  }
+
+  async fn with_profiles(pool: &MySqlPool, records: Vec<FriendShip>, other: fn(&FriendShip) -> i64) -> Result<Vec<FriendRequestRes>, AppError> {
+    let ids: Vec<i64> = records.iter().map(other).collect();
+    let mut profiles = UserService::find_profiles(pool, &ids).await?;
+
+    Ok(records.into_iter().map(|friendship| {
+      let user = profiles.remove(&other(&friendship));
+      FriendRequestRes { friendship, user }
+    }).collect())
+  }
+
+  /// label_id 不为空时只返回该标签下的好友
+  pub async fn list_friends(pool: &MySqlPool, user_id: i64, label_id: Option<i64>) -> Result<Vec<PublicUser>, AppError> {
+    let friends = sqlx::query_as::<_, User>(
       "
       SELECT u.* FROM users u
       JOIN friendships f
         ON (f.requester_id = u.id OR f.receiver_id = u.id)
       WHERE f.status = 'accepted'
         AND (f.requester_id = ? OR f.receiver_id = ?)
-        AND u.id != ?"
+        AND u.id != ?
+        AND (? IS NULL OR u.id IN (
+          SELECT m.friend_id FROM friend_label_members m
+          JOIN friend_labels l ON l.id = m.label_id
+          WHERE l.id = ? AND l.user_id = ?
+        ))"
     )
     .bind(user_id)
     .bind(user_id)
     .bind(user_id)
+    .bind(label_id)
+    .bind(label_id)
+    .bind(user_id)
     .fetch_all(pool)
     .await
-    .map_err(|e| AppError::Internal(e.to_string()))
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(friends.into_iter().map(|u| PublicUser::from_user(u, true)).collect())
   }
 
-  #[allow(dead_code)]
   pub async fn is_friend(pool: &MySqlPool, requester_id: i64, receiver_id: i64) -> Result<bool, AppError> {
     let result = sqlx::query_as::<_, FriendShip>(
       "SELECT * from friendships WHERE status = 'accepted' AND ((requester_id = ? AND receiver_id = ?) OR (requester_id = ? AND receiver_id = ?))"
@@ -136,4 +289,90 @@ impl FriendShipService {
     }
   }
 
+  /// 共享笔记、文件夹前校验对象：必须是存在且未注销的好友，任意一方拉黑都不行
+  pub async fn ensure_share_targets(pool: &MySqlPool, owner_id: i64, user_ids: &[i64]) -> Result<(), AppError> {
+    for user_id in user_ids.iter().filter(|u| **u != owner_id) {
+      let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE id = ? AND deleted_at IS NULL")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+      if exists == 0 {
+        return Err(AppError::BadRequest(format!("用户 {} 不存在", user_id)));
+      }
+      if BlockService::is_blocked_either(pool, owner_id, *user_id).await? {
+        return Err(AppError::BadRequest(format!("无法共享给用户 {}", user_id)));
+      }
+      if !Self::is_friend(pool, owner_id, *user_id).await? {
+        return Err(AppError::BadRequest(format!("用户 {} 不是您的好友", user_id)));
+      }
+    }
+    Ok(())
+  }
+
+  /// 好友推荐：好友的好友 + 同群成员，按 共同好友数 * 2 + 共同群聊数 排序
+  /// 排除自己、已有好友关系 (好友/待处理/已拒绝) 以及任意一方拉黑的用户
+  pub async fn suggestions(pool: &MySqlPool, user_id: i64, limit: i64) -> Result<Vec<FriendSuggestion>, AppError> {
+    sqlx::query_as::<_, FriendSuggestion>(
+      "
+      WITH my_friends AS (
+        SELECT IF(requester_id = ?, receiver_id, requester_id) AS friend_id
+        FROM friendships
+        WHERE status = 'accepted' AND (requester_id = ? OR receiver_id = ?)
+      ),
+      mutual AS (
+        SELECT IF(f.requester_id = mf.friend_id, f.receiver_id, f.requester_id) AS candidate_id,
+               COUNT(*) AS mutual_friends
+        FROM friendships f
+        JOIN my_friends mf ON (f.requester_id = mf.friend_id OR f.receiver_id = mf.friend_id)
+        WHERE f.status = 'accepted'
+        GROUP BY candidate_id
+      ),
+      shared AS (
+        SELECT other.user_id AS candidate_id, COUNT(DISTINCT other.conversation_id) AS shared_groups
+        FROM conversation_member me
+        JOIN conversations c ON c.id = me.conversation_id AND c.type = 1
+        JOIN conversation_member other ON other.conversation_id = me.conversation_id AND other.user_id != me.user_id
+        WHERE me.user_id = ?
+        GROUP BY other.user_id
+      ),
+      candidates AS (
+        SELECT candidate_id FROM mutual
+        UNION
+        SELECT candidate_id FROM shared
+      )
+      SELECT u.*,
+             COALESCE(m.mutual_friends, 0) AS mutual_friends,
+             COALESCE(s.shared_groups, 0) AS shared_groups
+      FROM candidates ca
+      JOIN users u ON u.id = ca.candidate_id
+      LEFT JOIN mutual m ON m.candidate_id = ca.candidate_id
+      LEFT JOIN shared s ON s.candidate_id = ca.candidate_id
+      WHERE u.id != ?
+        AND NOT EXISTS (
+          SELECT 1 FROM friendships p
+          WHERE (p.requester_id = ? AND p.receiver_id = u.id) OR (p.requester_id = u.id AND p.receiver_id = ?)
+        )
+        AND NOT EXISTS (
+          SELECT 1 FROM user_blocks b
+          WHERE (b.blocker_id = ? AND b.blocked_id = u.id) OR (b.blocker_id = u.id AND b.blocked_id = ?)
+        )
+      ORDER BY mutual_friends * 2 + shared_groups DESC, u.id
+      LIMIT ?"
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
 }
\ No newline at end of file
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Component;
+
+use actix_web::web;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sqlx::MySqlPool;
+use validator::Validate;
+use zip::ZipArchive;
+use crate::error::AppError;
+use crate::models::{ContentFormat, CreateFolder, CreateNote, ImportFormat, Note};
+use crate::services::{FolderService, NoteService, TagService};
+
+/// 一次导入的笔记数上限
+const MAX_NOTES: usize = 1000;
+/// zip 解压后的总大小上限，防止压缩炸弹
+const MAX_UNCOMPRESSED_BYTES: u64 = 50 * 1024 * 1024;
+const TITLE_MAX_CHARS: usize = 200;
+const FOLDER_NAME_MAX_CHARS: usize = 128;
+const UNTITLED: &str = "未命名笔记";
+
+/// ENML 里结束后需要换行的块级标签
+const ENML_BLOCK_TAGS: &[&[u8]] = &[
+    b"div", b"p", b"li", b"tr", b"blockquote", b"pre", b"table",
+    b"h1", b"h2", b"h3", b"h4", b"h5", b"h6",
+];
+
+/// 解析出来还没入库的笔记，dirs 是 zip 包里相对导入目标的目录
+struct ParsedNote {
+    dirs: Vec<String>,
+    note: CreateNote,
+}
+
+pub struct ImportService;
+
+impl ImportService {
+    /// 导入到 folder_id 下 (为空时是根目录)，zip 里的子目录会建成同名子文件夹
+    /// 解析在阻塞线程池里做；全部解析、校验 (包括标签名) 通过后才开始写库，
+    /// 写库中途失败时删掉这次已经导入的笔记和新建的文件夹，要么全部导入要么都不导入
+    pub async fn import(
+        pool: &MySqlPool,
+        user_id: i64,
+        format: ImportFormat,
+        folder_id: Option<i64>,
+        filename: Option<String>,
+        data: web::Bytes,
+    ) -> Result<Vec<Note>, AppError> {
+        if let Some(folder_id) = folder_id {
+            FolderService::find_owned(pool, folder_id, user_id).await?;
+        }
+
+        let mut parsed = web::block(move || match format {
+            ImportFormat::Md => {
+                let stem = filename.as_deref().map(Self::file_stem);
+                Ok(vec![ParsedNote {
+                    dirs: Vec::new(),
+                    note: Self::parse_markdown(&String::from_utf8_lossy(&data), stem, ContentFormat::Markdown),
+                }])
+            }
+            ImportFormat::Zip => Self::parse_zip(&data),
+            ImportFormat::Enex => Self::parse_enex(&data),
+        })
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))??;
+
+        if parsed.is_empty() {
+            return Err(AppError::BadRequest("没有找到可导入的笔记".to_string()));
+        }
+        for item in &mut parsed {
+            item.note.tags = TagService::normalize(&item.note.tags)?;
+            item.note.validate()?;
+        }
+
+        let mut notes = Vec::with_capacity(parsed.len());
+        let mut created_folders = Vec::new();
+        if let Err(e) = Self::write(pool, user_id, folder_id, parsed, &mut notes, &mut created_folders).await {
+            Self::rollback(pool, user_id, &notes, &created_folders).await;
+            return Err(e);
+        }
+        Ok(notes)
+    }
+
+    async fn write(
+        pool: &MySqlPool,
+        user_id: i64,
+        folder_id: Option<i64>,
+        parsed: Vec<ParsedNote>,
+        notes: &mut Vec<Note>,
+        created_folders: &mut Vec<i64>,
+    ) -> Result<(), AppError> {
+        let mut folders: HashMap<Vec<String>, i64> = HashMap::new();
+        for ParsedNote { dirs, mut note } in parsed {
+            note.folder_id = Self::ensure_folders(pool, user_id, folder_id, &dirs, &mut folders, created_folders).await?;
+            notes.push(NoteService::create(pool, note, user_id).await?);
+        }
+        Ok(())
+    }
+
+    /// 撤销已经写入的部分；这里再出错只记日志，返回的仍是最初的错误
+    async fn rollback(pool: &MySqlPool, user_id: i64, notes: &[Note], created_folders: &[i64]) {
+        for note in notes {
+            if let Err(e) = NoteService::purge(pool, note.id).await {
+                tracing::error!("导入失败后删除笔记 {} 失败：{}", note.id, e);
+            }
+        }
+        // 子文件夹后创建，倒序删除
+        for folder_id in created_folders.iter().rev() {
+            if let Err(e) = FolderService::delete(pool, *folder_id, user_id).await {
+                tracing::error!("导入失败后删除文件夹 {} 失败：{}", folder_id, e);
+            }
+        }
+    }
+
+    /// 逐级找到或创建 dirs 对应的文件夹，已存在的同名文件夹直接复用
+    async fn ensure_folders(
+        pool: &MySqlPool,
+        user_id: i64,
+        root_id: Option<i64>,
+        dirs: &[String],
+        cache: &mut HashMap<Vec<String>, i64>,
+        created: &mut Vec<i64>,
+    ) -> Result<Option<i64>, AppError> {
+        let mut parent_id = root_id;
+        for depth in 1..=dirs.len() {
+            let path = &dirs[..depth];
+            if let Some(id) = cache.get(path) {
+                parent_id = Some(*id);
+                continue;
+            }
+
+            let name: String = path[depth - 1].chars().take(FOLDER_NAME_MAX_CHARS).collect();
+            let existing = sqlx::query_scalar::<_, i64>(
+                "SELECT id FROM folders WHERE user_id = ? AND parent_id <=> ? AND name = ? LIMIT 1"
+            )
+                .bind(user_id)
+                .bind(parent_id)
+                .bind(&name)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = FolderService::create(pool, CreateFolder { name, parent_id }, user_id).await?.id;
+                    created.push(id);
+                    id
+                }
+            };
+            cache.insert(path.to_vec(), id);
+            parent_id = Some(id);
+        }
+        Ok(parent_id)
+    }
+
+    /// 支持导出时写的 front matter (title / tags)；没有标题时依次用一级标题、文件名
+    fn parse_markdown(text: &str, stem: Option<&str>, content_format: ContentFormat) -> CreateNote {
+        let text = text.trim_start_matches('\u{feff}');
+        let (meta, body) = Self::split_front_matter(text);
+
+        let mut title = None;
+        let mut tags = Vec::new();
+        for (key, value) in meta {
+            match key {
+                "title" => title = Some(Self::meta_string(value)),
+                "tags" => tags = Self::meta_list(value),
+                _ => {}
+            }
+        }
+
+        let heading = match content_format {
+            ContentFormat::Markdown => body.lines().find_map(|line| line.strip_prefix("# ")).map(|h| h.trim().to_string()),
+            ContentFormat::Plain => None,
+        };
+        let title = title
+            .filter(|t| !t.trim().is_empty())
+            .or(heading.filter(|h| !h.is_empty()))
+            .or(stem.filter(|s| !s.trim().is_empty()).map(str::to_string))
+            .unwrap_or_else(|| UNTITLED.to_string());
+
+        CreateNote {
+            title: title.trim().chars().take(TITLE_MAX_CHARS).collect(),
+            content: Some(body.to_string()),
+            content_format,
+            folder_id: None,
+            tags,
+        }
+    }
+
+    /// `---` 包围的 `key: value` 行；没有 front matter 时原样返回正文
+    fn split_front_matter(text: &str) -> (Vec<(&str, &str)>, &str) {
+        let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+            return (Vec::new(), text);
+        };
+        let Some(end) = rest.find("\n---") else {
+            return (Vec::new(), text);
+        };
+
+        let meta = rest[..end]
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let body = rest[end + 4..].split_once('\n').map(|(_, body)| body).unwrap_or("");
+        (meta, body.trim_matches(|c| c == '\n' || c == '\r'))
+    }
+
+    /// 导出时值是 JSON 字符串，手写的也可能不带引号
+    fn meta_string(value: &str) -> String {
+        if value.starts_with('"') && let Ok(s) = serde_json::from_str::<String>(value) {
+            return s;
+        }
+        value.trim_matches(|c| c == '"' || c == '\'').to_string()
+    }
+
+    /// `["a","b"]` 或 `a, b`
+    fn meta_list(value: &str) -> Vec<String> {
+        if value.starts_with('[') && let Ok(list) = serde_json::from_str::<Vec<String>>(value) {
+            return list;
+        }
+        value
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .map(|tag| tag.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    fn file_stem(filename: &str) -> &str {
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+        name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name)
+    }
+
+    /// .md / .markdown 按 Markdown、.txt 按纯文本导入，其他文件和隐藏文件忽略
+    fn parse_zip(data: &[u8]) -> Result<Vec<ParsedNote>, AppError> {
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|e| AppError::BadRequest(format!("zip 文件无法解析: {}", e)))?;
+
+        let mut notes = Vec::new();
+        let mut total: u64 = 0;
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|e| AppError::BadRequest(format!("zip 文件无法解析: {}", e)))?;
+            if file.is_dir() {
+                continue;
+            }
+            // enclosed_name 会拒绝 `..` 和绝对路径
+            let Some(path) = file.enclosed_name() else { continue };
+            let components: Vec<String> = path
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect();
+            if components.iter().any(|c| c.starts_with('.') || c == "__MACOSX") {
+                continue;
+            }
+            let Some((name, dirs)) = components.split_last() else { continue };
+            let Some((stem, ext)) = name.rsplit_once('.') else { continue };
+            let content_format = match ext.to_lowercase().as_str() {
+                "md" | "markdown" => ContentFormat::Markdown,
+                "txt" => ContentFormat::Plain,
+                _ => continue,
+            };
+
+            if notes.len() >= MAX_NOTES {
+                return Err(AppError::BadRequest(format!("一次最多导入 {} 篇笔记", MAX_NOTES)));
+            }
+            let mut buf = Vec::new();
+            (&mut file)
+                .take(MAX_UNCOMPRESSED_BYTES - total + 1)
+                .read_to_end(&mut buf)
+                .map_err(|e| AppError::BadRequest(format!("zip 文件无法解析: {}", e)))?;
+            total += buf.len() as u64;
+            if total > MAX_UNCOMPRESSED_BYTES {
+                return Err(AppError::BadRequest("zip 解压后超过 50MB".to_string()));
+            }
+
+            notes.push(ParsedNote {
+                dirs: dirs.to_vec(),
+                note: Self::parse_markdown(&String::from_utf8_lossy(&buf), Some(stem), content_format),
+            });
+        }
+        Ok(notes)
+    }
+
+    /// Evernote 导出格式：每个 `<note>` 取 title、content (ENML) 和 tag，正文转成纯文本
+    fn parse_enex(data: &[u8]) -> Result<Vec<ParsedNote>, AppError> {
+        let text = std::str::from_utf8(data).map_err(|_| AppError::BadRequest("ENEX 文件必须是 UTF-8 编码".to_string()))?;
+        let invalid = |e: String| AppError::BadRequest(format!("ENEX 文件无法解析: {}", e));
+
+        let mut reader = Reader::from_str(text);
+        let mut notes = Vec::new();
+        let mut current: Option<CreateNote> = None;
+        let mut field: Option<Vec<u8>> = None;
+        let mut value = String::new();
+        loop {
+            match reader.read_event().map_err(|e| invalid(e.to_string()))? {
+                Event::Start(e) => match e.name().as_ref() {
+                    b"note" => {
+                        current = Some(CreateNote {
+                            title: String::new(),
+                            content: None,
+                            content_format: ContentFormat::Plain,
+                            folder_id: None,
+                            tags: Vec::new(),
+                        });
+                    }
+                    name @ (b"title" | b"content" | b"tag") if current.is_some() => {
+                        field = Some(name.to_vec());
+                        value.clear();
+                    }
+                    _ => {}
+                },
+                Event::Text(e) if field.is_some() => {
+                    value.push_str(&e.unescape_with(Self::resolve_entity).map_err(|e| invalid(e.to_string()))?);
+                }
+                Event::CData(e) if field.is_some() => {
+                    value.push_str(&e.decode().map_err(|e| invalid(e.to_string()))?);
+                }
+                Event::End(e) => match e.name().as_ref() {
+                    b"note" => {
+                        let Some(mut note) = current.take() else { continue };
+                        if note.title.trim().is_empty() {
+                            note.title = UNTITLED.to_string();
+                        }
+                        if notes.len() >= MAX_NOTES {
+                            return Err(AppError::BadRequest(format!("一次最多导入 {} 篇笔记", MAX_NOTES)));
+                        }
+                        notes.push(ParsedNote { dirs: Vec::new(), note });
+                    }
+                    name if field.as_deref() == Some(name) => {
+                        field = None;
+                        let Some(note) = current.as_mut() else { continue };
+                        match name {
+                            b"title" => note.title = value.trim().chars().take(TITLE_MAX_CHARS).collect(),
+                            b"content" => note.content = Some(Self::enml_to_text(&value)),
+                            _ => {
+                                let tag = value.trim();
+                                if !tag.is_empty() {
+                                    note.tags.push(tag.to_string());
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(notes)
+    }
+
+    /// 块级标签换行，待办 `<en-todo checked="true"/>` 转成 `[x]`，其余标签丢弃
+    fn enml_to_text(enml: &str) -> String {
+        let mut reader = Reader::from_str(enml);
+        let mut text = String::new();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e) | Event::Empty(e)) => match e.name().as_ref() {
+                    b"en-todo" => {
+                        let checked = e.try_get_attribute("checked")
+                            .ok()
+                            .flatten()
+                            .is_some_and(|attr| attr.value.as_ref() == b"true");
+                        text.push_str(if checked { "[x] " } else { "[ ] " });
+                    }
+                    b"br" => text.push('\n'),
+                    b"li" => text.push_str("• "),
+                    _ => {}
+                },
+                Ok(Event::End(e)) if ENML_BLOCK_TAGS.contains(&e.name().as_ref()) => text.push('\n'),
+                Ok(Event::Text(e)) => text.push_str(&e.unescape_with(Self::resolve_entity).unwrap_or_default()),
+                Ok(Event::CData(e)) => text.push_str(&e.decode().unwrap_or_default()),
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+
+        while text.contains("\n\n\n") {
+            text = text.replace("\n\n\n", "\n\n");
+        }
+        text.trim().to_string()
+    }
+
+    /// ENML 允许 HTML 实体，只处理常见的，其他的丢掉
+    fn resolve_entity(entity: &str) -> Option<&'static str> {
+        match entity {
+            "nbsp" => Some(" "),
+            "mdash" => Some("—"),
+            "ndash" => Some("–"),
+            "hellip" => Some("…"),
+            "ldquo" => Some("“"),
+            "rdquo" => Some("”"),
+            "lsquo" => Some("‘"),
+            "rsquo" => Some("’"),
+            "copy" => Some("©"),
+            _ => Some(""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImportService, UNTITLED};
+    use crate::models::ContentFormat;
+
+    #[test]
+    fn front_matter_lf_and_crlf() {
+        let (meta, body) = ImportService::split_front_matter("---\ntitle: a\ntags: [\"x\"]\n---\n\nbody");
+        assert_eq!(meta, [("title", "a"), ("tags", "[\"x\"]")]);
+        assert_eq!(body, "body");
+
+        let (meta, body) = ImportService::split_front_matter("---\r\ntitle: a\r\n---\r\nbody\r\n");
+        assert_eq!(meta, [("title", "a")]);
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn front_matter_missing_or_unterminated() {
+        assert_eq!(ImportService::split_front_matter("# hello"), (Vec::new(), "# hello"));
+        let text = "---\ntitle: a\nno closing";
+        assert_eq!(ImportService::split_front_matter(text), (Vec::new(), text));
+    }
+
+    #[test]
+    fn meta_list_json_and_bare() {
+        assert_eq!(ImportService::meta_list("[\"a\", \"b,c\"]"), ["a", "b,c"]);
+        assert_eq!(ImportService::meta_list("a, 'b' , \"c\",,"), ["a", "b", "c"]);
+        assert_eq!(ImportService::meta_list("[a, b]"), ["a", "b"]);
+        assert!(ImportService::meta_list("").is_empty());
+    }
+
+    #[test]
+    fn meta_string_quoted() {
+        assert_eq!(ImportService::meta_string("\"a \\\"b\\\"\""), "a \"b\"");
+        assert_eq!(ImportService::meta_string("'plain'"), "plain");
+        assert_eq!(ImportService::meta_string("plain"), "plain");
+    }
+
+    #[test]
+    fn markdown_bom_and_title_fallbacks() {
+        let note = ImportService::parse_markdown("\u{feff}---\r\ntitle: \"标题\"\r\ntags: a, b\r\n---\r\n正文", None, ContentFormat::Markdown);
+        assert_eq!(note.title, "标题");
+        assert_eq!(note.tags, ["a", "b"]);
+        assert_eq!(note.content.as_deref(), Some("正文"));
+
+        let note = ImportService::parse_markdown("intro\n# Heading\ntext", Some("file"), ContentFormat::Markdown);
+        assert_eq!(note.title, "Heading");
+        let note = ImportService::parse_markdown("# not a heading", Some("file"), ContentFormat::Plain);
+        assert_eq!(note.title, "file");
+        let note = ImportService::parse_markdown("", None, ContentFormat::Markdown);
+        assert_eq!(note.title, UNTITLED);
+    }
+
+    #[test]
+    fn markdown_title_truncated() {
+        let note = ImportService::parse_markdown(&format!("# {}", "长".repeat(300)), None, ContentFormat::Markdown);
+        assert_eq!(note.title.chars().count(), 200);
+    }
+
+    #[test]
+    fn enex_notes() {
+        let enex = r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>First</title>
+    <content><![CDATA[<en-note><div>a&nbsp;b</div><div><en-todo checked="true"/>done</div><ul><li>item</li></ul></en-note>]]></content>
+    <tag>t1</tag>
+    <tag> </tag>
+    <tag>t2</tag>
+  </note>
+  <note>
+    <title>  </title>
+    <content></content>
+  </note>
+</en-export>"#;
+        let notes = ImportService::parse_enex(enex.as_bytes()).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].note.title, "First");
+        assert_eq!(notes[0].note.tags, ["t1", "t2"]);
+        assert_eq!(notes[0].note.content.as_deref(), Some("a b\n[x] done\n• item"));
+        assert_eq!(notes[1].note.title, UNTITLED);
+    }
+
+    #[test]
+    fn enex_rejects_invalid() {
+        assert!(ImportService::parse_enex(&[0xff, 0xfe]).is_err());
+        assert!(ImportService::parse_enex(b"<en-export><note><title>x</note>").is_err());
+    }
+}
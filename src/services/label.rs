@@ -0,0 +1,182 @@
+use serde::Serialize;
+use sqlx::{MySqlPool, QueryBuilder};
+use sqlx::prelude::FromRow;
+
+use crate::error::AppError;
+use crate::models::FriendLabel;
+use crate::services::{BlockService, FriendShipService};
+
+#[derive(FromRow, Serialize)]
+pub struct LabelRes {
+  #[sqlx(flatten)]
+  #[serde(flatten)]
+  pub label: FriendLabel,
+  pub member_count: i64
+}
+
+pub struct FriendLabelService;
+
+impl FriendLabelService {
+  pub async fn create(pool: &MySqlPool, user_id: i64, name: &str) -> Result<FriendLabel, AppError> {
+    Self::ensure_name_free(pool, user_id, name).await?;
+
+    let result = sqlx::query(
+      "INSERT INTO friend_labels (user_id, name) VALUES (?, ?)"
+    )
+    .bind(user_id)
+    .bind(name)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::find_owned(pool, result.last_insert_id() as i64, user_id).await
+  }
+
+  pub async fn list(pool: &MySqlPool, user_id: i64) -> Result<Vec<LabelRes>, AppError> {
+    sqlx::query_as::<_, LabelRes>(
+      "SELECT l.*, COUNT(m.friend_id) AS member_count
+      FROM friend_labels l
+      LEFT JOIN friend_label_members m ON m.label_id = l.id
+      WHERE l.user_id = ?
+      GROUP BY l.id
+      ORDER BY l.name"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  /// 标签只对创建者可见，别人的标签一律当作不存在
+  pub async fn find_owned(pool: &MySqlPool, label_id: i64, user_id: i64) -> Result<FriendLabel, AppError> {
+    sqlx::query_as::<_, FriendLabel>(
+      "SELECT * FROM friend_labels WHERE id = ? AND user_id = ?"
+    )
+    .bind(label_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .ok_or_else(|| AppError::NotFound("标签不存在".to_string()))
+  }
+
+  pub async fn rename(pool: &MySqlPool, label_id: i64, user_id: i64, name: &str) -> Result<FriendLabel, AppError> {
+    let label = Self::find_owned(pool, label_id, user_id).await?;
+    if label.name == name {
+      return Ok(label);
+    }
+    Self::ensure_name_free(pool, user_id, name).await?;
+
+    sqlx::query("UPDATE friend_labels SET name = ? WHERE id = ?")
+      .bind(name)
+      .bind(label_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::find_owned(pool, label_id, user_id).await
+  }
+
+  pub async fn delete(pool: &MySqlPool, label_id: i64, user_id: i64) -> Result<(), AppError> {
+    Self::find_owned(pool, label_id, user_id).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("DELETE FROM friend_label_members WHERE label_id = ?")
+      .bind(label_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("DELETE FROM friend_labels WHERE id = ?")
+      .bind(label_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 只能把好友加入标签，和自己有拉黑关系的好友也不行
+  pub async fn add_members(pool: &MySqlPool, label_id: i64, user_id: i64, friend_ids: &[i64]) -> Result<(), AppError> {
+    Self::find_owned(pool, label_id, user_id).await?;
+
+    for friend_id in friend_ids {
+      if !FriendShipService::is_friend(pool, user_id, *friend_id).await? {
+        return Err(AppError::BadRequest(format!("用户 {} 不是您的好友", friend_id)));
+      }
+      if BlockService::is_blocked_either(pool, user_id, *friend_id).await? {
+        return Err(AppError::BadRequest(format!("无法把用户 {} 加入标签", friend_id)));
+      }
+    }
+
+    for friend_id in friend_ids {
+      sqlx::query(
+        "INSERT IGNORE INTO friend_label_members (label_id, friend_id) VALUES (?, ?)"
+      )
+      .bind(label_id)
+      .bind(friend_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+
+    Ok(())
+  }
+
+  pub async fn remove_member(pool: &MySqlPool, label_id: i64, user_id: i64, friend_id: i64) -> Result<(), AppError> {
+    Self::find_owned(pool, label_id, user_id).await?;
+
+    sqlx::query("DELETE FROM friend_label_members WHERE label_id = ? AND friend_id = ?")
+      .bind(label_id)
+      .bind(friend_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 把标签展开为好友 id (去重)，用于笔记共享和创建群聊
+  /// 任意一个标签不属于 user_id 都返回 NotFound
+  pub async fn resolve_members(pool: &MySqlPool, user_id: i64, label_ids: &[i64]) -> Result<Vec<i64>, AppError> {
+    if label_ids.is_empty() {
+      return Ok(Vec::new());
+    }
+    for label_id in label_ids {
+      Self::find_owned(pool, *label_id, user_id).await?;
+    }
+
+    let mut query = QueryBuilder::new(
+      "SELECT DISTINCT friend_id FROM friend_label_members WHERE label_id IN ("
+    );
+    let mut separated = query.separated(", ");
+    for label_id in label_ids {
+      separated.push_bind(*label_id);
+    }
+    separated.push_unseparated(")");
+
+    query.build_query_scalar::<i64>()
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  async fn ensure_name_free(pool: &MySqlPool, user_id: i64, name: &str) -> Result<(), AppError> {
+    let exists = sqlx::query_scalar::<_, i64>(
+      "SELECT COUNT(*) FROM friend_labels WHERE user_id = ? AND name = ?"
+    )
+    .bind(user_id)
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if exists > 0 {
+      return Err(AppError::BadRequest("标签已存在".to_string()));
+    }
+    Ok(())
+  }
+}
\ No newline at end of file
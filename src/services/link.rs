@@ -0,0 +1,235 @@
+use sqlx::MySqlPool;
+use crate::error::AppError;
+use crate::models::{BrokenLink, LinkEdge, LinkedNote, NoteGraph, NoteLink};
+use crate::services::NoteService;
+use crate::utils::Markdown;
+
+/// 笔记之间的 `[[...]]` 链接，只在同一个用户自己的笔记之间解析
+pub struct LinkService;
+
+impl LinkService {
+    /// 按正文整体重建笔记的出链
+    pub async fn sync(pool: &MySqlPool, user_id: i64, note_id: i64, content: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM note_links WHERE source_id = ?")
+            .bind(note_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for target in Markdown::wiki_links(content) {
+            let target_id = Self::resolve(pool, user_id, &target).await?;
+            sqlx::query("INSERT INTO note_links (source_id, target, target_id) VALUES (?, ?, ?)")
+                .bind(note_id)
+                .bind(&target)
+                .bind(target_id)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 新建笔记或改标题后，之前指向这个标题的断链接上
+    pub async fn resolve_title(pool: &MySqlPool, user_id: i64, note_id: i64, title: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE note_links l JOIN notes s ON s.id = l.source_id
+             SET l.target_id = ?
+             WHERE s.user_id = ? AND l.target = ? AND l.target_id IS NULL"
+        )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(title)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 标题改了之后，把其他笔记里按旧标题写的 `[[旧标题]]` 改成新标题；按 id 写的链接不受影响
+    pub async fn rename(pool: &MySqlPool, user_id: i64, note_id: i64, title: &str) -> Result<(), AppError> {
+        let links = sqlx::query_as::<_, (i64, String)>(
+            "SELECT DISTINCT l.source_id, l.target FROM note_links l JOIN notes s ON s.id = l.source_id
+             WHERE l.target_id = ? AND s.user_id = ? AND l.target <> ?"
+        )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(title)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for (source_id, target) in links {
+            if target.parse::<i64>() == Ok(note_id) {
+                continue;
+            }
+            let source = NoteService::find_by_id(pool, source_id).await?;
+            let Some(content) = source.content.as_deref() else { continue };
+            let content = Self::replace_target(content, &target, title);
+
+            sqlx::query("UPDATE notes SET content = ?, excerpt = ? WHERE id = ?")
+                .bind(&content)
+                .bind(source.content_format.excerpt(&content))
+                .bind(source_id)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            Self::sync(pool, user_id, source_id, &content).await?;
+        }
+
+        Self::resolve_title(pool, user_id, note_id, title).await
+    }
+
+    /// 出链，断链的 target_id 为空
+    pub async fn outgoing(pool: &MySqlPool, note_id: i64) -> Result<Vec<NoteLink>, AppError> {
+        sqlx::query_as::<_, NoteLink>(
+            "SELECT l.target, t.id AS target_id, t.title AS target_title
+             FROM note_links l LEFT JOIN notes t ON t.id = l.target_id AND t.deleted_at IS NULL
+             WHERE l.source_id = ? ORDER BY l.id"
+        )
+            .bind(note_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// 链接到这篇笔记的笔记，只返回调用者自己的 (共享给别人时不暴露作者的其他笔记)
+    pub async fn backlinks(pool: &MySqlPool, note_id: i64, user_id: i64) -> Result<Vec<LinkedNote>, AppError> {
+        sqlx::query_as::<_, LinkedNote>(
+            "SELECT DISTINCT s.id, s.title, s.excerpt FROM note_links l JOIN notes s ON s.id = l.source_id
+             WHERE l.target_id = ? AND s.user_id = ? AND s.deleted_at IS NULL ORDER BY s.id"
+        )
+            .bind(note_id)
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// 自己笔记里的全部断链 (目标不存在或在回收站里)
+    pub async fn broken(pool: &MySqlPool, user_id: i64) -> Result<Vec<BrokenLink>, AppError> {
+        sqlx::query_as::<_, BrokenLink>(
+            "SELECT s.id AS source_id, s.title AS source_title, l.target
+             FROM note_links l
+             JOIN notes s ON s.id = l.source_id AND s.deleted_at IS NULL
+             LEFT JOIN notes t ON t.id = l.target_id AND t.deleted_at IS NULL
+             WHERE s.user_id = ? AND t.id IS NULL
+             ORDER BY s.id, l.id"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn graph(pool: &MySqlPool, user_id: i64) -> Result<NoteGraph, AppError> {
+        let nodes = sqlx::query_as::<_, LinkedNote>(
+            "SELECT id, title, excerpt FROM notes WHERE user_id = ? AND deleted_at IS NULL ORDER BY id"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let edges = sqlx::query_as::<_, LinkEdge>(
+            "SELECT DISTINCT l.source_id, l.target_id
+             FROM note_links l
+             JOIN notes s ON s.id = l.source_id AND s.deleted_at IS NULL
+             JOIN notes t ON t.id = l.target_id AND t.deleted_at IS NULL
+             WHERE s.user_id = ?
+             ORDER BY l.source_id, l.target_id"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(NoteGraph { nodes, edges })
+    }
+
+    /// 把正文里目标是 old 的 `[[...]]` 改成 new，保留 `|` 后面的显示文字；解析规则和 `Markdown::wiki_links` 一致
+    fn replace_target(content: &str, old: &str, new: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find("[[") {
+            result.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("]]") else { break };
+            let inner = &rest[..end];
+            if inner.contains('\n') || inner.contains("[[") {
+                continue;
+            }
+
+            let (target, alias) = match inner.split_once('|') {
+                Some((target, alias)) => (target, Some(alias)),
+                None => (inner, None),
+            };
+            if target.trim() == old {
+                result.push_str(new);
+                if let Some(alias) = alias {
+                    result.push('|');
+                    result.push_str(alias);
+                }
+            } else {
+                result.push_str(inner);
+            }
+            result.push_str("]]");
+            rest = &rest[end + 2..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// `[[123]]` 先按自己笔记的 id 找，找不到再按标题；同名笔记取最早的
+    async fn resolve(pool: &MySqlPool, user_id: i64, target: &str) -> Result<Option<i64>, AppError> {
+        if let Ok(id) = target.parse::<i64>() {
+            let found = sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE id = ? AND user_id = ? AND deleted_at IS NULL")
+                .bind(id)
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+
+        sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE user_id = ? AND title = ? AND deleted_at IS NULL ORDER BY id LIMIT 1")
+            .bind(user_id)
+            .bind(target)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkService;
+
+    #[test]
+    fn replace_target_keeps_alias() {
+        assert_eq!(
+            LinkService::replace_target("see [[Old]] and [[Old|别名]]", "Old", "New"),
+            "see [[New]] and [[New|别名]]"
+        );
+    }
+
+    #[test]
+    fn replace_target_matches_trimmed_target() {
+        assert_eq!(LinkService::replace_target("[[ Old ]] [[ Old |x]]", "Old", "New"), "[[New]] [[New|x]]");
+    }
+
+    #[test]
+    fn replace_target_leaves_other_links() {
+        assert_eq!(
+            LinkService::replace_target("[[Older]] [[Old2|Old]] [[old]]", "Old", "New"),
+            "[[Older]] [[Old2|Old]] [[old]]"
+        );
+    }
+
+    #[test]
+    fn replace_target_nested_and_broken() {
+        assert_eq!(LinkService::replace_target("[[a [[Old]] c]]", "Old", "New"), "[[a [[New]] c]]");
+        assert_eq!(LinkService::replace_target("[[Old\nline]] [[Old", "Old", "New"), "[[Old\nline]] [[Old");
+    }
+}
@@ -0,0 +1,152 @@
+use sqlx::MySqlPool;
+
+use crate::config::LoginThrottle;
+use crate::error::AppError;
+
+/// 锁定期间的请求只做审计，不计入失败次数
+const REASON_LOCKED: &str = "locked";
+
+/// 登录失败原因，存在 login_attempts.reason
+#[derive(Debug, Clone, Copy)]
+pub enum LoginFailure {
+  UnknownUser,
+  BadPassword,
+  BadTwoFactor,
+  /// 公开笔记的访问密码错误
+  BadSharePassword,
+}
+
+impl LoginFailure {
+  fn as_str(self) -> &'static str {
+    match self {
+      LoginFailure::UnknownUser => "unknown_user",
+      LoginFailure::BadPassword => "bad_password",
+      LoginFailure::BadTwoFactor => "bad_2fa",
+      LoginFailure::BadSharePassword => "bad_share_password",
+    }
+  }
+}
+
+/// 登录失败记录与限流
+/// - 按账号：统计最后一次成功登录之后的失败次数
+/// - 按 IP：不因成功登录清零，防止攻击者用自己的账号重置计数
+///
+/// 不存在的用户名同样计数，锁定行为不会暴露用户名是否存在
+pub struct LoginGuard;
+
+impl LoginGuard {
+  /// 被锁定时记录一条 `locked` 审计并返回 429
+  pub async fn check(pool: &MySqlPool, throttle: &LoginThrottle, username: &str, ip: &str) -> Result<(), AppError> {
+    let username = username.to_lowercase();
+
+    let (failures, elapsed) = sqlx::query_as::<_, (i64, Option<i64>)>(
+      "SELECT COUNT(*), TIMESTAMPDIFF(SECOND, MAX(created_at), NOW())
+      FROM login_attempts
+      WHERE username = ? AND success = FALSE AND reason <> ?
+        AND created_at > NOW() - INTERVAL ? SECOND
+        AND created_at > COALESCE(
+          (SELECT MAX(created_at) FROM login_attempts WHERE username = ? AND success = TRUE),
+          '1970-01-01'
+        )"
+    )
+      .bind(&username)
+      .bind(REASON_LOCKED)
+      .bind(throttle.window_secs)
+      .bind(&username)
+      .fetch_one(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    let account_wait = throttle.remaining_lockout(failures, throttle.max_failures, elapsed.unwrap_or(0));
+
+    let (failures, elapsed) = sqlx::query_as::<_, (i64, Option<i64>)>(
+      "SELECT COUNT(*), TIMESTAMPDIFF(SECOND, MAX(created_at), NOW())
+      FROM login_attempts
+      WHERE ip = ? AND success = FALSE AND reason <> ?
+        AND created_at > NOW() - INTERVAL ? SECOND"
+    )
+      .bind(ip)
+      .bind(REASON_LOCKED)
+      .bind(throttle.window_secs)
+      .fetch_one(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    let ip_wait = throttle.remaining_lockout(failures, throttle.ip_max_failures, elapsed.unwrap_or(0));
+
+    match account_wait.max(ip_wait) {
+      Some(wait) => {
+        Self::record(pool, &username, None, ip, false, REASON_LOCKED).await?;
+        Err(AppError::TooManyRequests(wait))
+      }
+      None => Ok(()),
+    }
+  }
+
+  pub async fn record_failure(pool: &MySqlPool, username: &str, user_id: Option<i64>, ip: &str, reason: LoginFailure) -> Result<(), AppError> {
+    tracing::warn!("登录失败：username={} ip={} reason={}", username, ip, reason.as_str());
+    Self::record(pool, &username.to_lowercase(), user_id, ip, false, reason.as_str()).await
+  }
+
+  pub async fn record_success(pool: &MySqlPool, username: &str, user_id: i64, ip: &str) -> Result<(), AppError> {
+    Self::record(pool, &username.to_lowercase(), Some(user_id), ip, true, "ok").await
+  }
+
+  async fn record(pool: &MySqlPool, username: &str, user_id: Option<i64>, ip: &str, success: bool, reason: &str) -> Result<(), AppError> {
+    sqlx::query(
+      "INSERT INTO login_attempts (username, user_id, ip, success, reason) VALUES (?, ?, ?, ?, ?)"
+    )
+      .bind(username)
+      .bind(user_id)
+      .bind(ip)
+      .bind(success)
+      .bind(reason)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::LoginThrottle;
+
+  fn throttle() -> LoginThrottle {
+    LoginThrottle { max_failures: 5, ip_max_failures: 20, base_lockout_secs: 60, max_lockout_secs: 900, window_secs: 3600 }
+  }
+
+  #[test]
+  fn no_lockout_below_threshold() {
+    assert_eq!(throttle().remaining_lockout(4, 5, 0), None);
+  }
+
+  #[test]
+  fn lockout_doubles_per_extra_failure() {
+    let throttle = throttle();
+    assert_eq!(throttle.remaining_lockout(5, 5, 0), Some(60));
+    assert_eq!(throttle.remaining_lockout(6, 5, 0), Some(120));
+    assert_eq!(throttle.remaining_lockout(7, 5, 0), Some(240));
+  }
+
+  #[test]
+  fn lockout_is_capped() {
+    let throttle = throttle();
+    assert_eq!(throttle.remaining_lockout(9, 5, 0), Some(900));
+    // 指数封顶，失败次数很大也不会溢出
+    assert_eq!(throttle.remaining_lockout(10_000, 5, 0), Some(900));
+  }
+
+  #[test]
+  fn lockout_counts_down_from_last_failure() {
+    let throttle = throttle();
+    assert_eq!(throttle.remaining_lockout(6, 5, 100), Some(20));
+    assert_eq!(throttle.remaining_lockout(6, 5, 120), None);
+  }
+
+  #[test]
+  fn ip_threshold_is_separate() {
+    let throttle = throttle();
+    assert_eq!(throttle.remaining_lockout(10, throttle.ip_max_failures, 0), None);
+    assert_eq!(throttle.remaining_lockout(20, throttle.ip_max_failures, 0), Some(60));
+  }
+}
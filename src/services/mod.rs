@@ -1,11 +1,47 @@
 mod note;
+mod folder;
+mod tag;
+mod link;
 mod user;
 mod ws;
 mod conversation;
+mod conversation_note;
 mod friendship;
+mod block;
+mod label;
+mod account;
+mod two_factor;
+mod login_guard;
+mod sso;
+mod api_token;
+mod admin;
+mod export;
+mod deletion;
+mod import;
+mod publication;
+mod attachment;
+mod template;
 
 pub use note::NoteService;
+pub use folder::FolderService;
+pub use tag::TagService;
+pub use link::LinkService;
 pub use user::UserService;
 pub use ws::MessageRepository;
 pub use conversation::ConversationServices;
-pub use friendship::FriendShipService;
\ No newline at end of file
+pub use conversation_note::ConversationNoteService;
+pub use friendship::FriendShipService;
+pub use block::BlockService;
+pub use label::FriendLabelService;
+pub use account::AccountService;
+pub use two_factor::TwoFactorService;
+pub use login_guard::{LoginGuard, LoginFailure};
+pub use sso::SsoService;
+pub use api_token::{ApiTokenService, API_TOKEN_PREFIX};
+pub use admin::{AdminService, UserFilter};
+pub use export::ExportService;
+pub use deletion::AccountDeletionService;
+pub use import::ImportService;
+pub use publication::PublicationService;
+pub use attachment::AttachmentService;
+pub use template::TemplateService;
@@ -1,47 +1,191 @@
-use sqlx::MySqlPool;
+use sqlx::{MySqlPool, QueryBuilder};
 use crate::error::AppError;
 use crate::models::{Note, CreateNote, UpdateNote};
+use crate::services::{FolderService, FriendShipService, LinkService, TagService};
 
 pub struct NoteService;
 
 impl NoteService {
     pub async fn find_all(pool: &MySqlPool, user_id: i64) -> Result<Vec<Note>, AppError> {
-        sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE user_id = ? ORDER BY created_at DESC")
+        let notes = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE user_id = ? AND deleted_at IS NULL ORDER BY created_at DESC")
             .bind(user_id)
             .fetch_all(pool)
             .await
-            .map_err(|e| AppError::Internal(e.to_string()))
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::with_tags(pool, notes).await
     }
 
+    /// 按标签筛选自己的笔记；match_all 为 true 时要求同时带有所有标签，否则任意一个即可
+    /// 标签名不区分大小写，先去重再计数，`?tag=rust&tag=Rust` 等同于一个标签
+    pub async fn find_by_tags(pool: &MySqlPool, user_id: i64, tags: &[String], match_all: bool) -> Result<Vec<Note>, AppError> {
+        let tags = TagService::normalize(tags)?;
+        if tags.is_empty() {
+            return Self::find_all(pool, user_id).await;
+        }
+        let mut query = QueryBuilder::new(
+            "SELECT n.* FROM notes n
+             JOIN note_tags nt ON nt.note_id = n.id
+             JOIN tags t ON t.id = nt.tag_id
+             WHERE n.deleted_at IS NULL AND n.user_id = "
+        );
+        query.push_bind(user_id).push(" AND t.name IN (");
+        let mut separated = query.separated(", ");
+        for tag in &tags {
+            separated.push_bind(tag);
+        }
+        separated.push_unseparated(") GROUP BY n.id");
+        if match_all {
+            query.push(" HAVING COUNT(DISTINCT t.id) = ").push_bind(tags.len() as i64);
+        }
+        query.push(" ORDER BY n.created_at DESC");
+
+        let notes = query.build_query_as::<Note>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::with_tags(pool, notes).await
+    }
+
+    /// 回收站里的笔记也能查到，调用方按需检查 deleted_at
     pub async fn find_by_id(pool: &MySqlPool, id: i64) -> Result<Note, AppError> {
-        sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = ?")
+        let mut note = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = ?")
             .bind(id)
             .fetch_optional(pool)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?
-            .ok_or_else(|| AppError::NotFound(format!("Note {} not found", id)))
+            .ok_or_else(|| AppError::NotFound(format!("Note {} not found", id)))?;
+        note.tags = TagService::names_for_notes(pool, &[id]).await?.remove(&id).unwrap_or_default();
+        Ok(note)
+    }
+
+    /// 作者本人、被共享的用户、分享到的会话的成员、所在文件夹 (含上级) 被共享的用户可以读取，
+    /// 其他人一律 NotFound (不暴露笔记是否存在)
+    pub async fn find_accessible(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Note, AppError> {
+        let note = sqlx::query_as::<_, Note>(
+            "SELECT * FROM notes WHERE id = ? AND deleted_at IS NULL
+             AND (user_id = ?
+               OR EXISTS (SELECT 1 FROM note_shares s WHERE s.note_id = notes.id AND s.user_id = ?)
+               OR EXISTS (SELECT 1 FROM conversation_notes cn
+                          JOIN conversation_member cm ON cm.conversation_id = cn.conversation_id
+                          WHERE cn.note_id = notes.id AND cm.user_id = ?))"
+        )
+            .bind(id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if note.is_some() {
+            return Self::find_by_id(pool, id).await;
+        }
+
+        let note = Self::find_by_id(pool, id).await?;
+        if note.deleted_at.is_none() && let Some(folder_id) = note.folder_id && FolderService::is_shared_with(pool, folder_id, user_id).await? {
+            return Ok(note);
+        }
+        Err(AppError::NotFound(format!("Note {} not found", id)))
+    }
+
+    /// 文件夹里的笔记 (调用方已校验文件夹权限)；folder_id 为 None 时是自己根目录下的笔记
+    pub async fn find_in_folder(pool: &MySqlPool, folder_id: Option<i64>, user_id: i64) -> Result<Vec<Note>, AppError> {
+        let notes = match folder_id {
+            Some(folder_id) => sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE folder_id = ? AND deleted_at IS NULL ORDER BY created_at DESC")
+                .bind(folder_id)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE user_id = ? AND folder_id IS NULL AND deleted_at IS NULL ORDER BY created_at DESC")
+                .bind(user_id)
+                .fetch_all(pool)
+                .await,
+        }
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::with_tags(pool, notes).await
+    }
+
+    /// 只有作者本人可以修改、删除、共享；回收站里的笔记视为不存在
+    pub async fn find_owned(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Note, AppError> {
+        let note = Self::find_by_id(pool, id).await?;
+        if note.user_id != user_id || note.deleted_at.is_some() {
+            return Err(AppError::NotFound(format!("Note {} not found", id)));
+        }
+        Ok(note)
     }
 
     pub async fn create(pool: &MySqlPool, data: CreateNote, user_id: i64) -> Result<Note, AppError> {
         tracing::info!("Creating note: {:?}", data);
-        let result = sqlx::query("INSERT INTO notes (title, content, user_id) VALUES (?, ?, ?)")
+        if let Some(folder_id) = data.folder_id {
+            FolderService::find_owned(pool, folder_id, user_id).await?;
+        }
+        let excerpt = data.content.as_deref().map(|content| data.content_format.excerpt(content));
+        let result = sqlx::query("INSERT INTO notes (title, content, content_format, excerpt, user_id, folder_id) VALUES (?, ?, ?, ?, ?, ?)")
             .bind(&data.title)
             .bind(&data.content)
+            .bind(data.content_format)
+            .bind(excerpt)
             .bind(user_id)
+            .bind(data.folder_id)
             .execute(pool)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
         tracing::info!("Inserted, last_insert_id: {}", result.last_insert_id());
-        Self::find_by_id(pool, result.last_insert_id() as i64).await
+        let id = result.last_insert_id() as i64;
+        if !data.tags.is_empty() {
+            TagService::set_note_tags(pool, user_id, id, &data.tags).await?;
+        }
+        if let Some(content) = &data.content {
+            LinkService::sync(pool, user_id, id, content).await?;
+        }
+        LinkService::resolve_title(pool, user_id, id, &data.title).await?;
+        Self::find_by_id(pool, id).await
     }
 
-    pub async fn update(pool: &MySqlPool, id: i64, data: UpdateNote) -> Result<Note, AppError> {
-        // 先确认存在
-        Self::find_by_id(pool, id).await?;
+    pub async fn update(pool: &MySqlPool, id: i64, user_id: i64, data: UpdateNote) -> Result<Note, AppError> {
+        // 先确认存在且是自己的
+        let note = Self::find_owned(pool, id, user_id).await?;
+
+        // 正文或格式变了才重新生成摘要
+        let format = data.content_format.unwrap_or(note.content_format);
+        let excerpt = match (&data.content, data.content_format) {
+            (Some(content), _) => Some(format.excerpt(content)),
+            (None, Some(_)) => note.content.as_deref().map(|content| format.excerpt(content)),
+            (None, None) => note.excerpt,
+        };
 
-        sqlx::query("UPDATE notes SET title = COALESCE(?, title), content = COALESCE(?, content) WHERE id = ?")
+        sqlx::query(
+            "UPDATE notes SET title = COALESCE(?, title), content = COALESCE(?, content), content_format = ?, excerpt = ? WHERE id = ?"
+        )
             .bind(&data.title)
             .bind(&data.content)
+            .bind(format)
+            .bind(excerpt)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Some(tags) = &data.tags {
+            TagService::set_note_tags(pool, user_id, id, tags).await?;
+        }
+        if let Some(content) = &data.content {
+            LinkService::sync(pool, user_id, id, content).await?;
+        }
+        // 改标题：别人按旧标题写的链接跟着改，指向新标题的断链接上
+        if let Some(title) = &data.title && *title != note.title {
+            LinkService::rename(pool, user_id, id, title).await?;
+        }
+
+        Self::find_by_id(pool, id).await
+    }
+
+    /// 移动到自己的另一个文件夹，None 为根目录
+    pub async fn move_to(pool: &MySqlPool, id: i64, user_id: i64, folder_id: Option<i64>) -> Result<Note, AppError> {
+        Self::find_owned(pool, id, user_id).await?;
+        if let Some(folder_id) = folder_id {
+            FolderService::find_owned(pool, folder_id, user_id).await?;
+        }
+
+        sqlx::query("UPDATE notes SET folder_id = ? WHERE id = ?")
+            .bind(folder_id)
             .bind(id)
             .execute(pool)
             .await
@@ -50,9 +194,11 @@ impl NoteService {
         Self::find_by_id(pool, id).await
     }
 
-    pub async fn delete(pool: &MySqlPool, id: i64) -> Result<(), AppError> {
-        let result = sqlx::query("DELETE FROM notes WHERE id = ?")
+    /// 移到回收站，共享和标签保留，恢复后照旧
+    pub async fn delete(pool: &MySqlPool, id: i64, user_id: i64) -> Result<(), AppError> {
+        let result = sqlx::query("UPDATE notes SET deleted_at = NOW() WHERE id = ? AND user_id = ? AND deleted_at IS NULL")
             .bind(id)
+            .bind(user_id)
             .execute(pool)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
@@ -60,7 +206,145 @@ impl NoteService {
         if result.rows_affected() == 0 {
             return Err(AppError::NotFound(format!("Note {} not found", id)));
         }
+        Ok(())
+    }
+
+    /// 回收站，最近删除的在前
+    pub async fn find_trash(pool: &MySqlPool, user_id: i64) -> Result<Vec<Note>, AppError> {
+        let notes = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE user_id = ? AND deleted_at IS NOT NULL ORDER BY deleted_at DESC")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::with_tags(pool, notes).await
+    }
+
+    pub async fn restore(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Note, AppError> {
+        let result = sqlx::query("UPDATE notes SET deleted_at = NULL WHERE id = ? AND user_id = ? AND deleted_at IS NOT NULL")
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Note {} not found in trash", id)));
+        }
+        Self::find_by_id(pool, id).await
+    }
+
+    /// 从回收站彻底删除
+    pub async fn delete_permanently(pool: &MySqlPool, id: i64, user_id: i64) -> Result<(), AppError> {
+        let note = Self::find_by_id(pool, id).await?;
+        if note.user_id != user_id || note.deleted_at.is_none() {
+            return Err(AppError::NotFound(format!("Note {} not found in trash", id)));
+        }
+        Self::purge(pool, id).await
+    }
+
+    /// 清空回收站，返回删除数量
+    pub async fn empty_trash(pool: &MySqlPool, user_id: i64) -> Result<u64, AppError> {
+        let ids = sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE user_id = ? AND deleted_at IS NOT NULL")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for id in &ids {
+            Self::purge(pool, *id).await?;
+        }
+        Ok(ids.len() as u64)
+    }
+
+    /// 后台任务：彻底删除在回收站里超过 retention_days 天的笔记
+    pub async fn purge_expired_trash(pool: &MySqlPool, retention_days: i64) -> Result<u64, AppError> {
+        let ids = sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE deleted_at < NOW() - INTERVAL ? DAY")
+            .bind(retention_days)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for id in &ids {
+            Self::purge(pool, *id).await?;
+        }
+        Ok(ids.len() as u64)
+    }
+
+    /// 不做权限检查的物理删除，连同共享、会话分享、公开链接、标签关联和出链；指向它的链接变成断链
+    pub async fn purge(pool: &MySqlPool, id: i64) -> Result<(), AppError> {
+        let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+        for statement in [
+            "DELETE FROM note_shares WHERE note_id = ?",
+            "DELETE FROM note_tags WHERE note_id = ?",
+            "DELETE FROM note_publications WHERE note_id = ?",
+            "DELETE FROM conversation_notes WHERE note_id = ?",
+            "DELETE FROM note_links WHERE source_id = ?",
+            "UPDATE note_links SET target_id = NULL WHERE target_id = ?",
+            "DELETE FROM notes WHERE id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 只能共享给好友；任意一个对象不存在、不是好友或有拉黑关系时整体拒绝
+    pub async fn share(pool: &MySqlPool, id: i64, owner_id: i64, user_ids: &[i64]) -> Result<(), AppError> {
+        Self::find_owned(pool, id, owner_id).await?;
+        FriendShipService::ensure_share_targets(pool, owner_id, user_ids).await?;
+
+        for user_id in user_ids.iter().filter(|u| **u != owner_id) {
+            sqlx::query("INSERT IGNORE INTO note_shares (note_id, user_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn unshare(pool: &MySqlPool, id: i64, owner_id: i64, user_id: i64) -> Result<(), AppError> {
+        Self::find_owned(pool, id, owner_id).await?;
+
+        sqlx::query("DELETE FROM note_shares WHERE note_id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
 
         Ok(())
     }
+
+    /// 别人共享给我的笔记
+    pub async fn find_shared_with(pool: &MySqlPool, user_id: i64) -> Result<Vec<Note>, AppError> {
+        let notes = sqlx::query_as::<_, Note>(
+            "SELECT n.* FROM notes n
+             JOIN note_shares s ON s.note_id = n.id
+             WHERE s.user_id = ? AND n.deleted_at IS NULL
+             ORDER BY s.created_at DESC"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::with_tags(pool, notes).await
+    }
+
+    async fn with_tags(pool: &MySqlPool, mut notes: Vec<Note>) -> Result<Vec<Note>, AppError> {
+        let ids: Vec<i64> = notes.iter().map(|n| n.id).collect();
+        let mut names = TagService::names_for_notes(pool, &ids).await?;
+        for note in &mut notes {
+            note.tags = names.remove(&note.id).unwrap_or_default();
+        }
+        Ok(notes)
+    }
 }
\ No newline at end of file
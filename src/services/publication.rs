@@ -0,0 +1,145 @@
+use actix_web::web;
+use bcrypt::{hash, DEFAULT_COST};
+use sqlx::MySqlPool;
+use crate::config::LoginThrottle;
+use crate::error::AppError;
+use crate::models::{NotePublication, PublicNote, PublishNote};
+use crate::services::{LoginFailure, LoginGuard, NoteService, UserService};
+use crate::utils::random_urlsafe;
+
+/// 笔记公开链接：不需要登录，知道链接 (和密码) 就能看
+pub struct PublicationService;
+
+impl PublicationService {
+    /// 发布；已发布过时保留原链接，只替换密码和有效期
+    pub async fn publish(pool: &MySqlPool, app_url: &str, note_id: i64, owner_id: i64, data: PublishNote) -> Result<NotePublication, AppError> {
+        NoteService::find_owned(pool, note_id, owner_id).await?;
+
+        let password_hash = match data.password {
+            Some(password) => Some(hash(password, DEFAULT_COST).map_err(|e| AppError::Internal(e.to_string()))?),
+            None => None,
+        };
+        let expires_at = data.expires_in_days.map(|days| chrono::Utc::now() + chrono::Duration::days(days));
+
+        sqlx::query(
+            "INSERT INTO note_publications (note_id, token, password_hash, expires_at) VALUES (?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE password_hash = VALUES(password_hash), expires_at = VALUES(expires_at)"
+        )
+            .bind(note_id)
+            .bind(random_urlsafe(24))
+            .bind(password_hash)
+            .bind(expires_at)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_for_note(pool, app_url, note_id, owner_id).await
+    }
+
+    pub async fn find_for_note(pool: &MySqlPool, app_url: &str, note_id: i64, owner_id: i64) -> Result<NotePublication, AppError> {
+        NoteService::find_owned(pool, note_id, owner_id).await?;
+
+        sqlx::query_as::<_, NotePublication>("SELECT * FROM note_publications WHERE note_id = ?")
+            .bind(note_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map(|publication| Self::with_url(publication, app_url))
+            .ok_or_else(|| AppError::NotFound("笔记未公开".to_string()))
+    }
+
+    /// 我公开的全部笔记 (不含回收站里的)
+    pub async fn find_all(pool: &MySqlPool, app_url: &str, owner_id: i64) -> Result<Vec<NotePublication>, AppError> {
+        let publications = sqlx::query_as::<_, NotePublication>(
+            "SELECT p.* FROM note_publications p JOIN notes n ON n.id = p.note_id
+             WHERE n.user_id = ? AND n.deleted_at IS NULL ORDER BY p.id DESC"
+        )
+            .bind(owner_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(publications.into_iter().map(|publication| Self::with_url(publication, app_url)).collect())
+    }
+
+    /// 取消公开，旧链接立即失效；再次发布会生成新链接
+    pub async fn unpublish(pool: &MySqlPool, note_id: i64, owner_id: i64) -> Result<(), AppError> {
+        NoteService::find_owned(pool, note_id, owner_id).await?;
+
+        let result = sqlx::query("DELETE FROM note_publications WHERE note_id = ?")
+            .bind(note_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("笔记未公开".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 匿名访问。链接不存在、已过期、笔记在回收站里都按不存在处理；
+    /// 密码错误和登录失败共用限流，按链接和 IP 计数
+    pub async fn view(
+        pool: &MySqlPool,
+        throttle: &LoginThrottle,
+        token: &str,
+        password: Option<&str>,
+        ip: &str,
+    ) -> Result<PublicNote, AppError> {
+        let publication = sqlx::query_as::<_, NotePublication>(
+            "SELECT * FROM note_publications WHERE token = ? AND (expires_at IS NULL OR expires_at > NOW())"
+        )
+            .bind(token)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound("链接不存在或已失效".to_string()))?;
+
+        let note = NoteService::find_by_id(pool, publication.note_id).await?;
+        if note.deleted_at.is_some() {
+            return Err(AppError::NotFound("链接不存在或已失效".to_string()));
+        }
+
+        if let Some(password_hash) = &publication.password_hash {
+            let key = format!("note-publication:{}", publication.id);
+            LoginGuard::check(pool, throttle, &key, ip).await?;
+            let Some(password) = password else {
+                return Err(AppError::Unauthorized("需要访问密码".to_string()));
+            };
+            if !UserService::verify_password(password, password_hash)? {
+                LoginGuard::record_failure(pool, &key, None, ip, LoginFailure::BadSharePassword).await?;
+                return Err(AppError::Unauthorized("访问密码错误".to_string()));
+            }
+        }
+
+        sqlx::query("UPDATE note_publications SET view_count = view_count + 1, last_viewed_at = NOW() WHERE id = ?")
+            .bind(publication.id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let author = sqlx::query_scalar::<_, String>("SELECT COALESCE(display_name, username) FROM users WHERE id = ?")
+            .bind(note.user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        web::block(move || PublicNote {
+            html: note.render_html(),
+            title: note.title,
+            content: note.content,
+            content_format: note.content_format,
+            author,
+            updated_at: note.updated_at,
+        })
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    fn with_url(mut publication: NotePublication, app_url: &str) -> NotePublication {
+        publication.url = format!("{}/p/{}", app_url, publication.token);
+        publication.has_password = publication.password_hash.is_some();
+        publication
+    }
+}
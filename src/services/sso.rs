@@ -0,0 +1,274 @@
+use chrono::Duration;
+use sqlx::{FromRow, MySqlPool};
+
+use crate::error::AppError;
+use crate::models::{RegisterRequest, User, UserIdentity};
+use crate::services::UserService;
+use crate::utils::{IdTokenClaims, OidcClient, Pkce, TokenUtil, random_urlsafe};
+
+#[derive(FromRow)]
+struct PendingState {
+  code_verifier: String,
+  nonce: String,
+  user_id: Option<i64>,
+}
+
+/// OIDC 单点登录：发起授权、处理回调、账号关联和自动创建用户
+pub struct SsoService;
+
+impl SsoService {
+  /// 生成 state / nonce / PKCE 并返回 IdP 授权地址；link_user_id 不为空表示给已登录用户关联身份
+  pub async fn begin(pool: &MySqlPool, client: &OidcClient, link_user_id: Option<i64>) -> Result<String, AppError> {
+    let state = random_urlsafe(32);
+    let nonce = random_urlsafe(16);
+    let pkce = Pkce::generate();
+
+    sqlx::query("DELETE FROM oidc_states WHERE expires_at < NOW()")
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query(
+      "INSERT INTO oidc_states (state_hash, code_verifier, nonce, user_id, expires_at) VALUES (?, ?, ?, ?, ?)"
+    )
+      .bind(TokenUtil::hash(&state))
+      .bind(&pkce.verifier)
+      .bind(&nonce)
+      .bind(link_user_id)
+      .bind(chrono::Utc::now() + Duration::minutes(10))
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    client.authorization_url(&state, &nonce, &pkce.challenge).await
+  }
+
+  /// 处理登录的 IdP 回调，返回登录的用户
+  /// - 已关联过：直接登录
+  /// - IdP 确认过的邮箱和本地已验证邮箱一致：自动关联
+  /// - 否则按配置自动创建用户
+  pub async fn complete(pool: &MySqlPool, client: &OidcClient, code: &str, state: &str, reserved_usernames: &[String]) -> Result<User, AppError> {
+    let pending = Self::consume_state(pool, state).await?;
+    // 关联请求只能由发起关联的用户在登录状态下完成，否则别人拿到授权地址就能把自己的 IdP 身份挂到发起人账号上
+    if pending.user_id.is_some() {
+      return Err(AppError::BadRequest("登录请求无效或已过期".to_string()));
+    }
+    let claims = client.exchange_code(code, &pending.code_verifier, &pending.nonce).await?;
+    let issuer = client.config.issuer.as_str();
+
+    if let Some(user_id) = Self::find_linked_user(pool, issuer, &claims.sub).await? {
+      return UserService::find_by_id(pool, user_id).await;
+    }
+
+    if let Some(email) = Self::verified_email(&claims)
+      && let Some(user) = UserService::find_by_verified_email(pool, email).await?
+    {
+      Self::link(pool, user.id, issuer, &claims).await?;
+      return Ok(user);
+    }
+
+    if !client.config.auto_provision {
+      return Err(AppError::Forbidden("该账号未关联，请先用已有账号登录后关联".to_string()));
+    }
+    let user = Self::provision(pool, &claims, reserved_usernames).await?;
+    Self::link(pool, user.id, issuer, &claims).await?;
+    Ok(user)
+  }
+
+  /// 处理关联的 IdP 回调；state 必须是当前登录用户自己发起的
+  pub async fn complete_link(pool: &MySqlPool, client: &OidcClient, code: &str, state: &str, user_id: i64) -> Result<(), AppError> {
+    let pending = Self::consume_state(pool, state).await?;
+    if pending.user_id != Some(user_id) {
+      return Err(AppError::BadRequest("关联请求无效或已过期".to_string()));
+    }
+    let claims = client.exchange_code(code, &pending.code_verifier, &pending.nonce).await?;
+    Self::link(pool, user_id, client.config.issuer.as_str(), &claims).await
+  }
+
+  pub async fn list_identities(pool: &MySqlPool, user_id: i64) -> Result<Vec<UserIdentity>, AppError> {
+    sqlx::query_as::<_, UserIdentity>(
+      "SELECT id, issuer, subject, email, created_at FROM user_identities WHERE user_id = ? ORDER BY id"
+    )
+      .bind(user_id)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  pub async fn unlink(pool: &MySqlPool, user_id: i64, identity_id: i64) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM user_identities WHERE id = ? AND user_id = ?")
+      .bind(identity_id)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+      return Err(AppError::NotFound("关联不存在".to_string()));
+    }
+    Ok(())
+  }
+
+  /// 读出来后 DELETE，删除成功的请求才能继续，同一个 state 只能回调一次
+  async fn consume_state(pool: &MySqlPool, state: &str) -> Result<PendingState, AppError> {
+    let state_hash = TokenUtil::hash(state);
+    let expired = || AppError::BadRequest("登录请求无效或已过期".to_string());
+
+    let pending = sqlx::query_as::<_, PendingState>(
+      "SELECT code_verifier, nonce, user_id FROM oidc_states WHERE state_hash = ? AND expires_at > NOW()"
+    )
+      .bind(&state_hash)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?
+      .ok_or_else(expired)?;
+
+    let result = sqlx::query("DELETE FROM oidc_states WHERE state_hash = ?")
+      .bind(&state_hash)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+    if result.rows_affected() == 0 {
+      return Err(expired());
+    }
+
+    Ok(pending)
+  }
+
+  async fn find_linked_user(pool: &MySqlPool, issuer: &str, subject: &str) -> Result<Option<i64>, AppError> {
+    sqlx::query_scalar::<_, i64>("SELECT user_id FROM user_identities WHERE issuer = ? AND subject = ?")
+      .bind(issuer)
+      .bind(subject)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
+  async fn link(pool: &MySqlPool, user_id: i64, issuer: &str, claims: &IdTokenClaims) -> Result<(), AppError> {
+    match Self::find_linked_user(pool, issuer, &claims.sub).await? {
+      Some(linked) if linked == user_id => return Ok(()),
+      Some(_) => return Err(AppError::BadRequest("该身份已关联其他账号".to_string())),
+      None => {}
+    }
+
+    sqlx::query("INSERT INTO user_identities (user_id, issuer, subject, email) VALUES (?, ?, ?, ?)")
+      .bind(user_id)
+      .bind(issuer)
+      .bind(&claims.sub)
+      .bind(&claims.email)
+      .execute(pool)
+      .await
+      .map_err(|e| {
+        if e.as_database_error().is_some_and(|d| d.is_unique_violation()) {
+          AppError::BadRequest("该账号已关联过此身份提供方".to_string())
+        } else {
+          AppError::Internal(e.to_string())
+        }
+      })?;
+
+    Ok(())
+  }
+
+  /// 用 preferred_username 或邮箱前缀生成用户名，重名时加随机后缀；密码随机，用户需要时可以走找回密码
+  async fn provision(pool: &MySqlPool, claims: &IdTokenClaims, reserved_usernames: &[String]) -> Result<User, AppError> {
+    let base = Self::username_base(claims);
+    // 保留用户名直接加后缀
+    let first = if reserved_usernames.contains(&base.to_lowercase()) { 1 } else { 0 };
+
+    for attempt in first..first + 5 {
+      let username = if attempt == 0 {
+        base.clone()
+      } else {
+        format!("{}-{}", base, &TokenUtil::generate()[..4])
+      };
+
+      let data = RegisterRequest {
+        username,
+        password: TokenUtil::generate(),
+        email: claims.email.clone(),
+      };
+      // 只有用户名被占用才换一个重试
+      match UserService::try_register(pool, data).await? {
+        Some(user) => {
+          // 邮箱已被别的账号验证 (并发关联) 时保持未验证
+          if claims.email_verified && user.email.is_some() {
+            let result = sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE id = ?")
+              .bind(user.id)
+              .execute(pool)
+              .await;
+            if let Err(e) = result
+              && !e.as_database_error().is_some_and(|d| d.is_unique_violation())
+            {
+              return Err(AppError::Internal(e.to_string()));
+            }
+          }
+          return Ok(user);
+        }
+        None => continue,
+      }
+    }
+
+    Err(AppError::Internal("无法为 SSO 用户生成唯一用户名".to_string()))
+  }
+
+  /// IdP 确认过的邮箱才能用来自动关联
+  fn verified_email(claims: &IdTokenClaims) -> Option<&str> {
+    claims.email.as_deref().filter(|_| claims.email_verified)
+  }
+
+  fn username_base(claims: &IdTokenClaims) -> String {
+    let raw = claims.preferred_username.as_deref()
+      .or_else(|| claims.email.as_deref().and_then(|email| email.split('@').next()))
+      .unwrap_or("user");
+
+    let mut username: String = raw.chars()
+      .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+      .take(26)
+      .collect();
+    while username.len() < 3 {
+      username.push('_');
+    }
+    username
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use validator::Validate;
+  use crate::models::RegisterRequest;
+  use crate::utils::IdTokenClaims;
+  use super::SsoService;
+
+  fn claims(email: Option<&str>, email_verified: bool, preferred_username: Option<&str>) -> IdTokenClaims {
+    IdTokenClaims {
+      sub: "subject-1".to_string(),
+      email: email.map(str::to_string),
+      email_verified,
+      preferred_username: preferred_username.map(str::to_string),
+      nonce: None,
+    }
+  }
+
+  #[test]
+  fn auto_link_requires_verified_email() {
+    assert_eq!(SsoService::verified_email(&claims(Some("a@example.com"), true, None)), Some("a@example.com"));
+    assert_eq!(SsoService::verified_email(&claims(Some("a@example.com"), false, None)), None);
+    assert_eq!(SsoService::verified_email(&claims(None, true, None)), None);
+  }
+
+  #[test]
+  fn username_base_is_a_valid_username() {
+    let cases = [
+      (None, Some("张三#deleted")),
+      (Some("ab@example.com"), None),
+      (None, Some(&"x".repeat(64)[..])),
+      (None, None),
+    ];
+    for (email, preferred) in cases {
+      let username = SsoService::username_base(&claims(email, true, preferred));
+      let data = RegisterRequest { username: username.clone(), password: "password123".to_string(), email: None };
+      assert!(data.validate().is_ok(), "{}", username);
+      assert!(!username.contains('#'));
+    }
+  }
+}
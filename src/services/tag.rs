@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use sqlx::{MySqlPool, QueryBuilder};
+use crate::error::AppError;
+use crate::models::Tag;
+
+/// 回收站里的笔记不计数
+const TAG_WITH_COUNT: &str =
+    "SELECT t.id, t.name, COUNT(n.id) AS note_count FROM tags t
+     LEFT JOIN note_tags nt ON nt.tag_id = t.id
+     LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL";
+
+pub struct TagService;
+
+impl TagService {
+    /// 自己的全部标签及使用次数
+    pub async fn find_all(pool: &MySqlPool, user_id: i64) -> Result<Vec<Tag>, AppError> {
+        sqlx::query_as::<_, Tag>(&format!("{} WHERE t.user_id = ? GROUP BY t.id, t.name ORDER BY t.name", TAG_WITH_COUNT))
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// 前缀补全，常用的排前面
+    pub async fn autocomplete(pool: &MySqlPool, user_id: i64, prefix: &str, limit: i64) -> Result<Vec<Tag>, AppError> {
+        let pattern = format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        sqlx::query_as::<_, Tag>(&format!(
+            "{} WHERE t.user_id = ? AND t.name LIKE ? GROUP BY t.id, t.name ORDER BY note_count DESC, t.name LIMIT ?",
+            TAG_WITH_COUNT
+        ))
+            .bind(user_id)
+            .bind(pattern)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn find_owned(pool: &MySqlPool, id: i64, user_id: i64) -> Result<Tag, AppError> {
+        sqlx::query_as::<_, Tag>(&format!("{} WHERE t.id = ? AND t.user_id = ? GROUP BY t.id, t.name", TAG_WITH_COUNT))
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound(format!("Tag {} not found", id)))
+    }
+
+    /// 重命名；新名字已被另一个标签占用时提示改用合并
+    pub async fn rename(pool: &MySqlPool, id: i64, user_id: i64, name: &str) -> Result<Tag, AppError> {
+        Self::find_owned(pool, id, user_id).await?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::BadRequest("标签名不能为空".to_string()));
+        }
+
+        let taken = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tags WHERE user_id = ? AND name = ? AND id <> ?")
+            .bind(user_id)
+            .bind(name)
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if taken > 0 {
+            return Err(AppError::BadRequest("标签已存在，请使用合并".to_string()));
+        }
+
+        sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_owned(pool, id, user_id).await
+    }
+
+    /// 把 source 标签下的笔记全部改打 target 标签，然后删除 source
+    pub async fn merge(pool: &MySqlPool, user_id: i64, source_ids: &[i64], target_id: i64) -> Result<Tag, AppError> {
+        Self::find_owned(pool, target_id, user_id).await?;
+        for source_id in source_ids {
+            Self::find_owned(pool, *source_id, user_id).await?;
+        }
+
+        let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+        for source_id in source_ids.iter().filter(|id| **id != target_id) {
+            sqlx::query("INSERT IGNORE INTO note_tags (note_id, tag_id) SELECT note_id, ? FROM note_tags WHERE tag_id = ?")
+                .bind(target_id)
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            sqlx::query("DELETE FROM note_tags WHERE tag_id = ?")
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            sqlx::query("DELETE FROM tags WHERE id = ?")
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Self::find_owned(pool, target_id, user_id).await
+    }
+
+    /// 删除标签，笔记本身不受影响
+    pub async fn delete(pool: &MySqlPool, id: i64, user_id: i64) -> Result<(), AppError> {
+        Self::find_owned(pool, id, user_id).await?;
+
+        sqlx::query("DELETE FROM note_tags WHERE tag_id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 整体替换笔记的标签，不存在的标签自动创建
+    pub async fn set_note_tags(pool: &MySqlPool, user_id: i64, note_id: i64, names: &[String]) -> Result<(), AppError> {
+        let names = Self::normalize(names)?;
+
+        sqlx::query("DELETE FROM note_tags WHERE note_id = ?")
+            .bind(note_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        for name in &names {
+            sqlx::query("INSERT IGNORE INTO tags (user_id, name) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(name)
+                .execute(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        let mut query = QueryBuilder::new("INSERT IGNORE INTO note_tags (note_id, tag_id) SELECT ");
+        query.push_bind(note_id)
+            .push(", id FROM tags WHERE user_id = ")
+            .push_bind(user_id)
+            .push(" AND name IN (");
+        let mut separated = query.separated(", ");
+        for name in &names {
+            separated.push_bind(name);
+        }
+        separated.push_unseparated(")");
+
+        query.build()
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 批量查询笔记的标签名，避免列表接口 N+1
+    pub async fn names_for_notes(pool: &MySqlPool, note_ids: &[i64]) -> Result<HashMap<i64, Vec<String>>, AppError> {
+        if note_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut query = QueryBuilder::new(
+            "SELECT nt.note_id, t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id IN ("
+        );
+        let mut separated = query.separated(", ");
+        for note_id in note_ids {
+            separated.push_bind(*note_id);
+        }
+        separated.push_unseparated(") ORDER BY t.name");
+
+        let rows = query.build_query_as::<(i64, String)>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let mut names: HashMap<i64, Vec<String>> = HashMap::new();
+        for (note_id, name) in rows {
+            names.entry(note_id).or_default().push(name);
+        }
+        Ok(names)
+    }
+
+    /// 去掉首尾空白和空标签，大小写不同的重复标签只保留第一个
+    pub fn normalize(names: &[String]) -> Result<Vec<String>, AppError> {
+        let mut result: Vec<String> = Vec::new();
+        for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            if name.chars().count() > 64 {
+                return Err(AppError::BadRequest("标签名最多 64 个字符".to_string()));
+            }
+            if !result.iter().any(|existing| existing.to_lowercase() == name.to_lowercase()) {
+                result.push(name.to_string());
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::{Datelike, FixedOffset, Utc, Weekday};
+use sqlx::MySqlPool;
+use crate::error::AppError;
+use crate::models::{CreateNote, CreateTemplate, NoteTemplate, UpdateTemplate};
+use crate::services::UserService;
+
+/// 每个用户自己的模板数上限，全局模板不计
+const TEMPLATES_PER_USER: i64 = 100;
+const TITLE_MAX_CHARS: usize = 200;
+/// 允许的时区偏移，UTC-12:00 到 UTC+14:00，单位分钟
+const UTC_OFFSET_MINUTES: std::ops::RangeInclusive<i32> = -720..=840;
+
+/// 笔记模板。`owner` 为 `None` 时操作全局模板，只有管理员接口会这样调用
+pub struct TemplateService;
+
+impl TemplateService {
+    /// 自己的模板和全局模板，全局的在前
+    pub async fn find_all(pool: &MySqlPool, user_id: i64) -> Result<Vec<NoteTemplate>, AppError> {
+        sqlx::query_as::<_, NoteTemplate>(
+            "SELECT * FROM note_templates WHERE user_id IS NULL OR user_id = ? ORDER BY user_id IS NOT NULL, id"
+        )
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn find_global(pool: &MySqlPool) -> Result<Vec<NoteTemplate>, AppError> {
+        sqlx::query_as::<_, NoteTemplate>("SELECT * FROM note_templates WHERE user_id IS NULL ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn create(pool: &MySqlPool, owner: Option<i64>, data: CreateTemplate) -> Result<NoteTemplate, AppError> {
+        if let Some(user_id) = owner {
+            let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM note_templates WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            if count >= TEMPLATES_PER_USER {
+                return Err(AppError::BadRequest(format!("最多只能创建 {} 个模板", TEMPLATES_PER_USER)));
+            }
+        }
+
+        let result = sqlx::query("INSERT INTO note_templates (user_id, name, title_pattern, content, content_format) VALUES (?, ?, ?, ?, ?)")
+            .bind(owner)
+            .bind(&data.name)
+            .bind(&data.title_pattern)
+            .bind(&data.content)
+            .bind(data.content_format)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::find_by_id(pool, result.last_insert_id() as i64).await
+    }
+
+    pub async fn update(pool: &MySqlPool, id: i64, owner: Option<i64>, data: UpdateTemplate) -> Result<NoteTemplate, AppError> {
+        Self::find_owned(pool, id, owner).await?;
+
+        sqlx::query(
+            "UPDATE note_templates SET
+                name = COALESCE(?, name),
+                title_pattern = COALESCE(?, title_pattern),
+                content = COALESCE(?, content),
+                content_format = COALESCE(?, content_format)
+             WHERE id = ?"
+        )
+            .bind(&data.name)
+            .bind(&data.title_pattern)
+            .bind(&data.content)
+            .bind(data.content_format)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::find_by_id(pool, id).await
+    }
+
+    /// 已经用模板创建的笔记不受影响
+    pub async fn delete(pool: &MySqlPool, id: i64, owner: Option<i64>) -> Result<NoteTemplate, AppError> {
+        let template = Self::find_owned(pool, id, owner).await?;
+        sqlx::query("DELETE FROM note_templates WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(template)
+    }
+
+    /// 按模板补全新笔记：没传标题时用模板标题，没传正文时用模板正文和格式；
+    /// 占位符按 `utc_offset` (分钟) 的本地时间替换
+    pub async fn apply(pool: &MySqlPool, id: i64, user_id: i64, utc_offset: i32, data: &mut CreateNote) -> Result<(), AppError> {
+        if !UTC_OFFSET_MINUTES.contains(&utc_offset) {
+            return Err(AppError::BadRequest("utc_offset 必须在 -720 到 840 之间".to_string()));
+        }
+        let template = sqlx::query_as::<_, NoteTemplate>(
+            "SELECT * FROM note_templates WHERE id = ? AND (user_id IS NULL OR user_id = ?)"
+        )
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound("模板不存在".to_string()))?;
+        let offset = FixedOffset::east_opt(utc_offset * 60)
+            .ok_or_else(|| AppError::BadRequest("utc_offset 超出范围".to_string()))?;
+        let user = UserService::find_by_id(pool, user_id).await?;
+
+        let now = Utc::now().with_timezone(&offset);
+        let mut values = vec![
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("time", now.format("%H:%M").to_string()),
+            ("datetime", now.format("%Y-%m-%d %H:%M").to_string()),
+            ("weekday", Self::weekday_name(now.weekday()).to_string()),
+            ("username", user.username.clone()),
+            ("name", user.display_name.clone().unwrap_or(user.username)),
+        ];
+
+        if data.title.trim().is_empty() {
+            data.title = Self::render(&template.title_pattern, &values).chars().take(TITLE_MAX_CHARS).collect();
+        }
+        if data.content.is_none() {
+            values.push(("title", data.title.clone()));
+            data.content = Some(Self::render(&template.content, &values));
+            data.content_format = template.content_format;
+        }
+        Ok(())
+    }
+
+    /// 一次扫描替换 `{{key}}`，替换进去的值不会再被展开；不认识的占位符原样保留
+    fn render(pattern: &str, values: &[(&str, String)]) -> String {
+        let mut result = String::with_capacity(pattern.len());
+        let mut rest = pattern;
+        while let Some(start) = rest.find("{{") {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let value = after.find("}}").and_then(|end| {
+                values.iter().find(|(key, _)| *key == &after[..end]).map(|(_, value)| (value, end))
+            });
+            match value {
+                Some((value, end)) => {
+                    result.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    result.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    fn weekday_name(weekday: Weekday) -> &'static str {
+        match weekday {
+            Weekday::Mon => "星期一",
+            Weekday::Tue => "星期二",
+            Weekday::Wed => "星期三",
+            Weekday::Thu => "星期四",
+            Weekday::Fri => "星期五",
+            Weekday::Sat => "星期六",
+            Weekday::Sun => "星期日",
+        }
+    }
+
+    async fn find_by_id(pool: &MySqlPool, id: i64) -> Result<NoteTemplate, AppError> {
+        sqlx::query_as::<_, NoteTemplate>("SELECT * FROM note_templates WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound("模板不存在".to_string()))
+    }
+
+    /// 只能改删自己的模板，全局模板对普通用户按不存在处理
+    async fn find_owned(pool: &MySqlPool, id: i64, owner: Option<i64>) -> Result<NoteTemplate, AppError> {
+        sqlx::query_as::<_, NoteTemplate>("SELECT * FROM note_templates WHERE id = ? AND user_id <=> ?")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound("模板不存在".to_string()))
+    }
+}
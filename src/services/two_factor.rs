@@ -0,0 +1,241 @@
+use serde::Serialize;
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::models::User;
+use crate::services::UserService;
+use crate::utils::{Totp, TokenUtil};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct TotpSetup {
+  pub secret: String,
+  /// `otpauth://` 链接，前端生成二维码
+  pub otpauth_uri: String,
+}
+
+/// 两步验证：TOTP 绑定/解绑、登录校验、一次性恢复码
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+  /// 生成新的密钥，确认之前不生效；重复调用会覆盖上一次未确认的密钥
+  pub async fn begin_enrollment(pool: &MySqlPool, user: &User, issuer: &str) -> Result<TotpSetup, AppError> {
+    if user.totp_enabled_at.is_some() {
+      return Err(AppError::BadRequest("已开启两步验证".to_string()));
+    }
+
+    let secret = Totp::generate_secret();
+    sqlx::query("UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ? AND totp_enabled_at IS NULL")
+      .bind(&secret)
+      .bind(user.id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(TotpSetup {
+      otpauth_uri: Totp::provisioning_uri(&secret, &user.username, issuer),
+      secret,
+    })
+  }
+
+  /// 用 App 生成的验证码确认绑定，返回恢复码明文 (只返回这一次)
+  pub async fn confirm_enrollment(pool: &MySqlPool, user_id: i64, code: &str) -> Result<Vec<String>, AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    if user.totp_enabled_at.is_some() {
+      return Err(AppError::BadRequest("已开启两步验证".to_string()));
+    }
+    let Some(secret) = user.totp_secret.as_deref() else {
+      return Err(AppError::BadRequest("请先获取两步验证密钥".to_string()));
+    };
+    let Some(step) = Totp::verify(secret, code, chrono::Utc::now().timestamp()) else {
+      return Err(AppError::Unauthorized("验证码错误".to_string()));
+    };
+
+    sqlx::query("UPDATE users SET totp_enabled_at = NOW(), totp_last_step = ? WHERE id = ?")
+      .bind(step)
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::replace_recovery_codes(pool, user_id).await
+  }
+
+  /// 关闭两步验证需要密码 + 验证码；被要求强制开启的用户不能关闭
+  pub async fn disable(pool: &MySqlPool, user_id: i64, password: &str, code: &str, enforced_globally: bool) -> Result<(), AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    if user.totp_enabled_at.is_none() {
+      return Err(AppError::BadRequest("未开启两步验证".to_string()));
+    }
+    if enforced_globally || user.two_factor_required {
+      return Err(AppError::Forbidden("管理员要求必须开启两步验证".to_string()));
+    }
+    if !UserService::verify_password(password, &user.password_hash)? {
+      return Err(AppError::Unauthorized("密码错误".to_string()));
+    }
+    Self::verify(pool, &user, code).await?;
+
+    sqlx::query("UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL WHERE id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = ?")
+      .bind(user_id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(())
+  }
+
+  /// 重新生成恢复码，旧的全部作废
+  pub async fn regenerate_recovery_codes(pool: &MySqlPool, user_id: i64, code: &str) -> Result<Vec<String>, AppError> {
+    let user = UserService::find_by_id(pool, user_id).await?;
+    if user.totp_enabled_at.is_none() {
+      return Err(AppError::BadRequest("未开启两步验证".to_string()));
+    }
+    Self::verify(pool, &user, code).await?;
+    Self::replace_recovery_codes(pool, user_id).await
+  }
+
+  /// 校验 6 位 TOTP 或恢复码，两者都只能用一次
+  pub async fn verify(pool: &MySqlPool, user: &User, code: &str) -> Result<(), AppError> {
+    let code = code.trim();
+    let accepted = if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+      Self::verify_totp(pool, user, code).await?
+    } else {
+      Self::use_recovery_code(pool, user.id, code).await?
+    };
+
+    if !accepted {
+      return Err(AppError::Unauthorized("验证码错误".to_string()));
+    }
+    Ok(())
+  }
+
+  /// 记录最后一次使用的时间窗口，同一个验证码不能在有效期内再用一次
+  async fn verify_totp(pool: &MySqlPool, user: &User, code: &str) -> Result<bool, AppError> {
+    let (Some(secret), Some(_)) = (user.totp_secret.as_deref(), user.totp_enabled_at) else {
+      return Ok(false);
+    };
+    let Some(step) = Self::totp_step(secret, user.totp_last_step, code, chrono::Utc::now().timestamp()) else {
+      return Ok(false);
+    };
+
+    // 条件更新兜住并发：两个请求同时用同一个验证码只有一个能成功
+    let result = sqlx::query(
+      "UPDATE users SET totp_last_step = ? WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)"
+    )
+      .bind(step)
+      .bind(user.id)
+      .bind(step)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(result.rows_affected() == 1)
+  }
+
+  /// 验证码匹配且时间窗口比上次用过的新才返回窗口序号
+  fn totp_step(secret: &str, last_step: Option<i64>, code: &str, now: i64) -> Option<i64> {
+    Totp::verify(secret, code, now).filter(|step| last_step.is_none_or(|last| *step > last))
+  }
+
+  async fn use_recovery_code(pool: &MySqlPool, user_id: i64, code: &str) -> Result<bool, AppError> {
+    let result = sqlx::query(
+      "UPDATE user_recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"
+    )
+      .bind(user_id)
+      .bind(TokenUtil::hash(&Self::normalize_recovery_code(code)))
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(result.rows_affected() == 1)
+  }
+
+  async fn replace_recovery_codes(pool: &MySqlPool, user_id: i64) -> Result<Vec<String>, AppError> {
+    let mut tx = pool.begin().await.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = ?")
+      .bind(user_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+      // xxxxx-xxxxx，大小写和横线在校验时忽略
+      let raw = &TokenUtil::generate()[..10];
+      let code = format!("{}-{}", &raw[..5], &raw[5..]);
+
+      sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES (?, ?)")
+        .bind(user_id)
+        .bind(TokenUtil::hash(&Self::normalize_recovery_code(&code)))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+      codes.push(code);
+    }
+
+    tx.commit().await.map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(codes)
+  }
+
+  fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(|c| *c != '-' && !c.is_whitespace()).collect::<String>().to_lowercase()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::utils::Totp;
+  use super::TwoFactorService;
+
+  /// RFC 6238 附录 B 的 SHA-1 密钥 "12345678901234567890"
+  const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+  #[test]
+  fn rfc6238_sha1_vectors() {
+    // RFC 给的是 8 位验证码，6 位取后六位
+    let vectors = [
+      (59, "287082"),
+      (1111111109, "081804"),
+      (1111111111, "050471"),
+      (1234567890, "005924"),
+      (2000000000, "279037"),
+      (20000000000, "353130"),
+    ];
+    for (time, code) in vectors {
+      assert_eq!(Totp::verify(SECRET, code, time), Some(time / 30), "T = {}", time);
+    }
+  }
+
+  #[test]
+  fn accepts_one_step_of_clock_skew() {
+    // 081804 是 T = 1111111109 所在窗口的验证码
+    let step = 1111111109 / 30;
+    for (offset, expected) in [(-2, None), (-1, Some(step)), (0, Some(step)), (1, Some(step)), (2, None)] {
+      assert_eq!(Totp::verify(SECRET, "081804", (step + offset) * 30), expected, "offset = {}", offset);
+    }
+  }
+
+  #[test]
+  fn rejects_wrong_code() {
+    assert_eq!(Totp::verify(SECRET, "287083", 59), None);
+    assert_eq!(Totp::verify(SECRET, "abcdef", 59), None);
+  }
+
+  #[test]
+  fn rejects_replayed_or_older_step() {
+    assert_eq!(TwoFactorService::totp_step(SECRET, None, "287082", 59), Some(1));
+    assert_eq!(TwoFactorService::totp_step(SECRET, Some(0), "287082", 59), Some(1));
+    assert_eq!(TwoFactorService::totp_step(SECRET, Some(1), "287082", 59), None);
+    // 用过第 2 个窗口后，还在容差内的第 1 个窗口验证码也不能再用
+    assert_eq!(TwoFactorService::totp_step(SECRET, Some(2), "287082", 89), None);
+  }
+}
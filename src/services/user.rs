@@ -1,24 +1,34 @@
-use sqlx::{MySqlPool};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use sqlx::{FromRow, MySqlPool, QueryBuilder};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use crate::error::AppError;
-use crate::models::{User, RegisterRequest};
+use crate::services::FriendShipService;
+use crate::models::{User, UserProfile, PublicUser, RegisterRequest, UpdateProfile, UpdatePrivacy};
+
+#[derive(FromRow)]
+struct SearchRow {
+  #[sqlx(flatten)]
+  user: User,
+  is_friend: bool
+}
+
+/// 用户不存在时也跑一次 bcrypt，登录耗时不暴露用户名是否存在
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+  hash("easynote-dummy-password", DEFAULT_COST).expect("bcrypt hash")
+});
 
 pub struct UserService;
 
 impl UserService {
   pub async fn register(pool: &MySqlPool, data: RegisterRequest) -> Result<User, AppError> {
-    let exists = sqlx::query_scalar::<_, i64>(
-      "SELECT COUNT(*) FROM users WHERE username = ?"
-    )
-      .bind(&data.username)
-      .fetch_one(pool)
-      .await
-      .map_err(|e| AppError::Internal(e.to_string()))?;
-
-    if exists > 0 {
-      return Err(AppError::BadRequest("用户名已存在".to_string()));
-    }
+    Self::try_register(pool, data).await?
+      .ok_or_else(|| AppError::BadRequest("用户名已存在".to_string()))
+  }
 
+  /// 用户名已被占用时返回 None，其它错误照常返回
+  pub async fn try_register(pool: &MySqlPool, data: RegisterRequest) -> Result<Option<User>, AppError> {
     let hashed_password = hash(&data.password, DEFAULT_COST)
       .map_err(|e| AppError::Internal(e.to_string()))?;
 
@@ -29,10 +39,14 @@ impl UserService {
       .bind(&hashed_password)
       .bind(&data.email)
       .execute(pool)
-      .await
-      .map_err(|e| AppError::Internal(e.to_string()))?;
+      .await;
 
-    Self::find_by_id(pool, result.last_insert_id() as i64).await
+    match result {
+      Ok(result) => Self::find_by_id(pool, result.last_insert_id() as i64).await.map(Some),
+      // 唯一索引 (username_lower) 兜底并发注册和大小写不同的重名
+      Err(e) if e.as_database_error().is_some_and(|d| d.is_unique_violation()) => Ok(None),
+      Err(e) => Err(AppError::Internal(e.to_string())),
+    }
   }
 
   pub async fn find_by_id(pool: &MySqlPool, id:i64) -> Result<User, AppError> {
@@ -53,20 +67,196 @@ impl UserService {
       .ok_or_else(|| AppError::NotFound("用户不存在".to_string()))
   }
 
+  /// 设置新密码并 token_version + 1 (所有旧 JWT 失效)，返回新的 token_version
+  pub async fn set_password(pool: &MySqlPool, id: i64, password: &str) -> Result<i32, AppError> {
+    let hashed_password = hash(password, DEFAULT_COST)
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query("UPDATE users SET password = ?, token_version = token_version + 1 WHERE id = ?")
+      .bind(&hashed_password)
+      .bind(id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Self::find_by_id(pool, id).await?.token_version)
+  }
+
+  /// 只匹配已验证的邮箱；未验证的邮箱谁都能填，不能用来定位账号
+  pub async fn find_by_verified_email(pool: &MySqlPool, email: &str) -> Result<Option<User>, AppError> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE verified_email = ?")
+      .bind(email)
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))
+  }
+
   pub fn verify_password(password: &str, hashed: &str) -> Result<bool, AppError> {
     verify(password, hashed)
       .map_err(|e| AppError::Internal(e.to_string()))
   }
 
-  pub async fn search(pool: &MySqlPool, q: &str) -> Result<Vec<User>, AppError> {
-    // todo!()
-    let pattern = format!("%{}%", q);
-    sqlx::query_as::<_, crate::models::User>(
-      "SELECT * FROM users WHERE username LIKE ?"
+  pub fn verify_dummy_password(password: &str) {
+    let _ = verify(password, &DUMMY_PASSWORD_HASH);
+  }
+
+  /// 用户目录搜索
+  /// - 含 `@` 按邮箱精确查找，看起来像手机号按手机号精确查找，受对方 contact_search 限制
+  /// - 其他按用户名/昵称前缀匹配，受对方 username_search 限制；完全匹配 > 用户名前缀 > 昵称前缀
+  ///
+  /// 不返回自己和任意一方拉黑的用户
+  pub async fn search(pool: &MySqlPool, viewer_id: i64, q: &str, limit: i64) -> Result<Vec<PublicUser>, AppError> {
+    let q = q.trim();
+    if q.is_empty() {
+      return Err(AppError::BadRequest("搜索关键字不能为空".to_string()));
+    }
+
+    let (condition, setting) = if q.contains('@') {
+      ("u.email = ?", "u.contact_search")
+    } else if Self::looks_like_phone(q) {
+      ("u.phone = ?", "u.contact_search")
+    } else {
+      ("(u.username LIKE ? OR u.display_name LIKE ?)", "u.username_search")
+    };
+    let pattern = format!("{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+
+    let sql = format!(
+      "SELECT * FROM (
+        SELECT u.*,
+          EXISTS(
+            SELECT 1 FROM friendships f
+            WHERE f.status = 'accepted'
+              AND ((f.requester_id = ? AND f.receiver_id = u.id) OR (f.requester_id = u.id AND f.receiver_id = ?))
+          ) AS is_friend,
+          {setting} AS search_setting,
+          CASE WHEN u.username = ? THEN 0 WHEN u.username LIKE ? THEN 1 ELSE 2 END AS search_rank
+        FROM users u
+        WHERE {condition}
+          AND u.id != ?
+          AND NOT EXISTS (
+            SELECT 1 FROM user_blocks b
+            WHERE (b.blocker_id = ? AND b.blocked_id = u.id) OR (b.blocker_id = u.id AND b.blocked_id = ?)
+          )
+      ) t
+      WHERE t.search_setting = 'everyone' OR (t.search_setting = 'friends' AND t.is_friend)
+      ORDER BY t.search_rank, CHAR_LENGTH(t.username), t.id
+      LIMIT ?"
+    );
+
+    let mut query = sqlx::query_as::<_, SearchRow>(&sql)
+      .bind(viewer_id)
+      .bind(viewer_id)
+      .bind(q)
+      .bind(&pattern);
+    query = if condition.contains("LIKE") {
+      query.bind(&pattern).bind(&pattern)
+    } else {
+      query.bind(q)
+    };
+
+    let rows = query
+      .bind(viewer_id)
+      .bind(viewer_id)
+      .bind(viewer_id)
+      .bind(limit)
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(rows.into_iter().map(|row| PublicUser::from_user(row.user, row.is_friend)).collect())
+  }
+
+  fn looks_like_phone(q: &str) -> bool {
+    let digits = q.chars().filter(|c| c.is_ascii_digit()).count();
+    digits >= 5 && q.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | ' '))
+  }
+
+  /// 查看其他用户的资料，邮箱/手机号按对方的隐私设置返回
+  pub async fn find_public(pool: &MySqlPool, viewer_id: i64, id: i64) -> Result<PublicUser, AppError> {
+    let user = Self::find_by_id(pool, id).await?;
+    let is_friend = viewer_id == id || FriendShipService::is_friend(pool, viewer_id, id).await?;
+    Ok(PublicUser::from_user(user, is_friend))
+  }
+
+  pub async fn update_privacy(pool: &MySqlPool, id: i64, data: UpdatePrivacy) -> Result<User, AppError> {
+    sqlx::query(
+      "UPDATE users SET
+        username_search = COALESCE(?, username_search),
+        contact_search = COALESCE(?, contact_search),
+        email_visibility = COALESCE(?, email_visibility)
+      WHERE id = ?"
     )
-    .bind(&pattern)
-    .fetch_all(pool)
-    .await
-    .map_err(|e| AppError::Internal(e.to_string()))
+      .bind(data.username_search)
+      .bind(data.contact_search)
+      .bind(data.email_visibility)
+      .bind(id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::find_by_id(pool, id).await
+  }
+
+  /// 空字符串表示清空该字段
+  pub async fn update_profile(pool: &MySqlPool, id: i64, data: UpdateProfile) -> Result<User, AppError> {
+    sqlx::query(
+      "UPDATE users SET
+        display_name = IF(? IS NULL, display_name, NULLIF(?, '')),
+        bio = IF(? IS NULL, bio, NULLIF(?, '')),
+        status_text = IF(? IS NULL, status_text, NULLIF(?, '')),
+        phone = IF(? IS NULL, phone, NULLIF(?, ''))
+      WHERE id = ?"
+    )
+      .bind(&data.display_name)
+      .bind(&data.display_name)
+      .bind(&data.bio)
+      .bind(&data.bio)
+      .bind(&data.status_text)
+      .bind(&data.status_text)
+      .bind(&data.phone)
+      .bind(&data.phone)
+      .bind(id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::find_by_id(pool, id).await
+  }
+
+  pub async fn update_avatar(pool: &MySqlPool, id: i64, avatar_url: &str) -> Result<User, AppError> {
+    sqlx::query("UPDATE users SET avatar_url = ? WHERE id = ?")
+      .bind(avatar_url)
+      .bind(id)
+      .execute(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Self::find_by_id(pool, id).await
+  }
+
+  pub async fn find_profile(pool: &MySqlPool, id: i64) -> Result<UserProfile, AppError> {
+    Self::find_by_id(pool, id).await.map(UserProfile::from)
+  }
+
+  /// 批量查询资料，用于给好友请求等列表补全用户信息
+  pub async fn find_profiles(pool: &MySqlPool, ids: &[i64]) -> Result<HashMap<i64, UserProfile>, AppError> {
+    if ids.is_empty() {
+      return Ok(HashMap::new());
+    }
+    let mut query = QueryBuilder::new(
+      "SELECT id, username, display_name, avatar_url, bio, status_text FROM users WHERE id IN ("
+    );
+    let mut separated = query.separated(", ");
+    for id in ids {
+      separated.push_bind(*id);
+    }
+    separated.push_unseparated(")");
+
+    let profiles = query.build_query_as::<UserProfile>()
+      .fetch_all(pool)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(profiles.into_iter().map(|p| (p.id, p)).collect())
   }
 }
\ No newline at end of file
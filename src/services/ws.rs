@@ -1,11 +1,19 @@
 use sqlx::{MySqlPool};
-use crate::{error::AppError};
+use crate::{error::AppError, models::{NoteCard, UserProfile}};
+use crate::services::ConversationNoteService;
 
 #[derive(sqlx::FromRow, serde::Serialize)]
 pub struct ChatMessage {
     pub sender_id: i64,
     pub content: String,
-    pub created_at: chrono::DateTime<chrono::Utc>
+    pub msg_type: String,
+    pub note_id: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[sqlx(flatten)]
+    pub sender: UserProfile,
+    /// 笔记卡片，取消分享或笔记被删除后为空
+    #[sqlx(skip)]
+    pub note: Option<NoteCard>
 }
 
 pub struct MessageRepository;
@@ -27,14 +35,30 @@ impl MessageRepository {
         .map_err(|e| AppError::Internal(e.to_string()))
     }
 
-    pub async fn get_recent(pool: &MySqlPool, conversation_id: i64, limit: i16 ) -> Result<Vec<ChatMessage>, AppError> {
-        sqlx::query_as(
-            "SELECT sender_id, content, created_at FROM messages WHERE conversation_id = ? ORDER BY created_at DESC LIMIT ?"
+    /// 只返回该用户清空聊天记录之后的消息，非成员拿不到任何历史，被拉黑用户的消息不返回
+    pub async fn get_recent(pool: &MySqlPool, conversation_id: i64, user_id: i64, limit: i16 ) -> Result<Vec<ChatMessage>, AppError> {
+        let mut messages: Vec<ChatMessage> = sqlx::query_as(
+            "SELECT m.sender_id, m.content, m.msg_type, m.note_id, m.created_at,
+                u.id, u.username, u.display_name, u.avatar_url, u.bio, u.status_text
+            FROM messages m
+            JOIN users u ON u.id = m.sender_id
+            JOIN conversation_member cm ON cm.conversation_id = m.conversation_id AND cm.user_id = ?
+            WHERE m.conversation_id = ? AND (cm.cleared_at IS NULL OR m.created_at > cm.cleared_at)
+              AND m.sender_id NOT IN (SELECT blocked_id FROM user_blocks WHERE blocker_id = cm.user_id)
+            ORDER BY m.created_at DESC LIMIT ?"
         )
-        .bind(&conversation_id)
-        .bind(&limit)
+        .bind(user_id)
+        .bind(conversation_id)
+        .bind(limit)
         .fetch_all(pool)
         .await
-        .map_err(|e| AppError::Internal(e.to_string()))
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let note_ids: Vec<i64> = messages.iter().filter_map(|m| m.note_id).collect();
+        let cards = ConversationNoteService::cards(pool, conversation_id, &note_ids).await?;
+        for message in &mut messages {
+            message.note = message.note_id.and_then(|id| cards.get(&id).cloned());
+        }
+        Ok(messages)
     }
-}
+}
\ No newline at end of file
@@ -6,12 +6,23 @@ use crate::error::AppError;
 pub struct Claims {
     pub sub: i64,         // 用户 ID
     pub exp: usize,       // 过期时间
+    #[serde(default)]
+    pub ver: i32,         // 签发时的 users.token_version，不一致说明已被吊销
+}
+
+/// 登录两步验证用的短期令牌，只能换取正式 token，不能访问其他接口
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChallengeClaims {
+    pub sub: i64,
+    pub exp: usize,
+    pub ver: i32,
+    pub purpose: String,
 }
 
 pub struct JwtUtil;
 
 impl JwtUtil {
-    pub fn generate_token(user_id: i64, secret: &str) -> Result<String, AppError> {
+    pub fn generate_token(user_id: i64, token_version: i32, secret: &str) -> Result<String, AppError> {
         let expiration = chrono::Utc::now()
             .checked_add_signed(chrono::Duration::hours(24))
             .expect("valid timestamp")
@@ -20,6 +31,7 @@ impl JwtUtil {
         let claims = Claims {
             sub: user_id,
             exp: expiration,
+            ver: token_version,
         };
 
         encode(
@@ -39,4 +51,42 @@ impl JwtUtil {
         .map(|data| data.claims)
         .map_err(|_| AppError::Unauthorized("Invalid token".to_string()))
     }
-}
\ No newline at end of file
+
+    /// 用 `secret:challenge` 签名，Auth 中间件用 `secret` 校验不会通过
+    pub fn generate_challenge(user_id: i64, token_version: i32, purpose: &str, secret: &str) -> Result<String, AppError> {
+        let expiration = chrono::Utc::now()
+            .checked_add_signed(chrono::Duration::minutes(5))
+            .expect("valid timestamp")
+            .timestamp() as usize;
+
+        let claims = ChallengeClaims {
+            sub: user_id,
+            exp: expiration,
+            ver: token_version,
+            purpose: purpose.to_string(),
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(Self::challenge_secret(secret).as_bytes()),
+        )
+        .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub fn verify_challenge(token: &str, purpose: &str, secret: &str) -> Result<ChallengeClaims, AppError> {
+        decode::<ChallengeClaims>(
+            token,
+            &DecodingKey::from_secret(Self::challenge_secret(secret).as_bytes()),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+        .ok()
+        .filter(|claims| claims.purpose == purpose)
+        .ok_or_else(|| AppError::Unauthorized("验证已过期，请重新登录".to_string()))
+    }
+
+    fn challenge_secret(secret: &str) -> String {
+        format!("{}:challenge", secret)
+    }
+}
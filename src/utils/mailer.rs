@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use actix_web::web;
+use futures::future::BoxFuture;
+use crate::error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// 发邮件的抽象，接入 SMTP / 第三方服务时实现这个 trait 即可
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), AppError>>;
+}
+
+/// 本地开发用：只打日志
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async move {
+            tracing::info!("📧 to: {} subject: {}\n{}", mail.to, mail.subject, mail.body);
+            Ok(())
+        })
+    }
+}
+
+/// 本地开发用：每封邮件写成 dir 下的一个 .eml 文件
+pub struct FileMailer {
+    pub dir: PathBuf,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), AppError>> {
+        let dir = self.dir.clone();
+        Box::pin(async move {
+            web::block(move || {
+                std::fs::create_dir_all(&dir)?;
+                let name = format!("{}-{}.eml", chrono::Utc::now().timestamp_millis(), mail.to.replace(['/', '\\'], "_"));
+                let content = format!("To: {}\nSubject: {}\n\n{}\n", mail.to, mail.subject, mail.body);
+                std::fs::write(dir.join(name), content)
+            })
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map_err(|e| AppError::Internal(e.to_string()))
+        })
+    }
+}
\ No newline at end of file
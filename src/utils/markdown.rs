@@ -0,0 +1,217 @@
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// 代码高亮只输出 class，不带内联样式，配色由客户端 CSS 决定
+const HIGHLIGHT_CLASS_PREFIX: &str = "hl-";
+/// 和笔记标题的长度上限一致
+const WIKI_LINK_MAX_CHARS: usize = 200;
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+/// 渲染结果一律经过白名单清洗：Markdown 里可以写原始 HTML，不能信任
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("span", ["class"])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("pre", ["class"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            // 任务列表只允许只读的复选框
+            ("input", "type") => (value == "checkbox").then_some(Cow::Borrowed(value)),
+            // 只保留高亮和语言标记用的 class，防止借用页面上的其他样式
+            (_, "class") => {
+                let classes: Vec<&str> = value
+                    .split_whitespace()
+                    .filter(|c| c.starts_with(HIGHLIGHT_CLASS_PREFIX) || c.starts_with("language-"))
+                    .collect();
+                (!classes.is_empty()).then(|| Cow::Owned(classes.join(" ")))
+            }
+            _ => Some(Cow::Borrowed(value)),
+        });
+    builder
+});
+
+/// Markdown 渲染：表格、任务列表、删除线、代码高亮，输出清洗过的 HTML
+pub struct Markdown;
+
+impl Markdown {
+    pub fn render_html(source: &str) -> String {
+        let mut events = Vec::new();
+        let mut code_block: Option<(String, String)> = None;
+
+        for event in Parser::new_ext(source, Self::options()) {
+            match (event, &mut code_block) {
+                (Event::Start(Tag::CodeBlock(kind)), None) => {
+                    let lang = match kind {
+                        CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    code_block = Some((lang, String::new()));
+                }
+                (Event::Text(text), Some((_, code))) => code.push_str(&text),
+                (Event::End(TagEnd::CodeBlock), Some(_)) => {
+                    if let Some((lang, code)) = code_block.take() {
+                        events.push(Event::Html(Self::highlight(&lang, &code).into()));
+                    }
+                }
+                (event, _) => events.push(event),
+            }
+        }
+
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+        SANITIZER.clean(&html).to_string()
+    }
+
+    /// 纯文本按空行分段，换行保留
+    pub fn render_plain(source: &str) -> String {
+        source
+            .split("\n\n")
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| format!("<p>{}</p>", Self::escape(p.trim()).replace('\n', "<br>")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 列表页用的纯文本摘要：去掉 Markdown 标记，空白合并，最多 max_chars 个字符
+    pub fn excerpt(source: &str, max_chars: usize) -> String {
+        let mut text = String::new();
+        for event in Parser::new_ext(source, Self::options()) {
+            match event {
+                Event::Text(t) | Event::Code(t) => text.push_str(&t),
+                Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+                _ => {}
+            }
+        }
+        Self::truncate(&text.split_whitespace().collect::<Vec<_>>().join(" "), max_chars)
+    }
+
+    /// 去掉 Markdown 标记，保留段落、列表、表格行的换行 (PDF 导出用)
+    pub fn plain_text(source: &str) -> String {
+        let mut text = String::new();
+        for event in Parser::new_ext(source, Self::options()) {
+            match event {
+                Event::Text(t) | Event::Code(t) => text.push_str(&t),
+                Event::Start(Tag::Item) => text.push_str("• "),
+                Event::TaskListMarker(done) => text.push_str(if done { "[x] " } else { "[ ] " }),
+                Event::SoftBreak => text.push(' '),
+                Event::HardBreak | Event::Rule => text.push('\n'),
+                Event::End(TagEnd::TableCell) => text.push_str(" | "),
+                Event::End(TagEnd::Item | TagEnd::TableRow | TagEnd::TableHead | TagEnd::CodeBlock) => text.push('\n'),
+                Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::List(_) | TagEnd::Table) => text.push_str("\n\n"),
+                _ => {}
+            }
+        }
+        while text.contains("\n\n\n") {
+            text = text.replace("\n\n\n", "\n\n");
+        }
+        text.trim().to_string()
+    }
+
+    /// `[[标题]]`、`[[标题|显示文字]]`、`[[id]]` 里的链接目标，按出现顺序去重
+    pub fn wiki_links(source: &str) -> Vec<String> {
+        let mut links: Vec<String> = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("[[") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("]]") else { break };
+            let inner = &rest[..end];
+            // 跨行的不算；`[[a [[b]]` 从里面的 `[[` 重新开始
+            if inner.contains('\n') || inner.contains("[[") {
+                continue;
+            }
+            rest = &rest[end + 2..];
+
+            let target = inner.split('|').next().unwrap_or("").trim();
+            if !target.is_empty() && target.chars().count() <= WIKI_LINK_MAX_CHARS && !links.iter().any(|l| l == target) {
+                links.push(target.to_string());
+            }
+        }
+        links
+    }
+
+    /// 纯文本笔记的摘要
+    pub fn plain_excerpt(source: &str, max_chars: usize) -> String {
+        Self::truncate(&source.split_whitespace().collect::<Vec<_>>().join(" "), max_chars)
+    }
+
+    fn truncate(text: &str, max_chars: usize) -> String {
+        match text.char_indices().nth(max_chars) {
+            Some((end, _)) => format!("{}…", text[..end].trim_end()),
+            None => text.to_string(),
+        }
+    }
+
+    fn options() -> Options {
+        Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH
+    }
+
+    /// 不认识的语言按纯文本处理，仍然会转义
+    fn highlight(lang: &str, code: &str) -> String {
+        let syntax = SYNTAXES
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(
+            syntax,
+            &SYNTAXES,
+            ClassStyle::SpacedPrefixed { prefix: HIGHLIGHT_CLASS_PREFIX },
+        );
+        for line in LinesWithEndings::from(code) {
+            if generator.parse_html_for_line_which_includes_newline(line).is_err() {
+                return format!("<pre><code>{}</code></pre>\n", Self::escape(code));
+            }
+        }
+
+        let class = if lang.is_empty() { String::new() } else { format!(" class=\"language-{}\"", Self::escape(lang)) };
+        format!("<pre><code{}>{}</code></pre>\n", class, generator.finalize())
+    }
+
+    pub fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Markdown;
+
+    #[test]
+    fn wiki_links_dedupes_and_strips_alias() {
+        assert_eq!(Markdown::wiki_links("see [[Foo]] and [[Bar|bar alias]] and [[Foo]]"), ["Foo", "Bar"]);
+    }
+
+    #[test]
+    fn wiki_links_nested_restarts_at_inner() {
+        assert_eq!(Markdown::wiki_links("[[a [[b]] c]]"), ["b"]);
+        assert_eq!(Markdown::wiki_links("[[a [[b]]"), ["b"]);
+    }
+
+    #[test]
+    fn wiki_links_skips_multiline_and_empty() {
+        assert_eq!(Markdown::wiki_links("[[broken\nline]] [[12]]"), ["12"]);
+        assert_eq!(Markdown::wiki_links("[[]] [[ x ]] [[|alias]]"), ["x"]);
+    }
+
+    #[test]
+    fn wiki_links_unterminated() {
+        assert!(Markdown::wiki_links("no links [[ unterminated").is_empty());
+    }
+
+    #[test]
+    fn wiki_links_length_limit() {
+        let max = "标".repeat(200);
+        let over = "标".repeat(201);
+        assert_eq!(Markdown::wiki_links(&format!("[[{}]] [[{}]]", max, over)), [max]);
+    }
+}
@@ -1,3 +1,17 @@
 mod jwt;
+mod storage;
+mod token;
+mod mailer;
+mod totp;
+mod oidc;
+mod markdown;
+mod pdf;
 
-pub use jwt::{JwtUtil, Claims};
\ No newline at end of file
+pub use jwt::{JwtUtil, Claims};
+pub use storage::Storage;
+pub use token::TokenUtil;
+pub use mailer::{Mail, Mailer, LogMailer, FileMailer};
+pub use totp::Totp;
+pub use oidc::{OidcClient, IdTokenClaims, Pkce, random_urlsafe};
+pub use markdown::Markdown;
+pub use pdf::Pdf;
\ No newline at end of file
@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const TITLE_SIZE: f32 = 18.0;
+const BODY_SIZE: f32 = 11.0;
+const LINE_HEIGHT: f32 = 1.5;
+
+/// 极简 PDF：只有文字，A4 自动换行分页
+/// 字体用阅读器自带的 STSong-Light (不嵌入，UniGB-UCS2-H 编码)，中英文都能显示，
+/// 超出 BMP 的字符 (emoji 等) 显示为 `?`
+pub struct Pdf;
+
+impl Pdf {
+    pub fn text_document(title: &str, body: &str) -> Vec<u8> {
+        let mut lines: Vec<(f32, String)> = Self::wrap(title, TITLE_SIZE)
+            .into_iter()
+            .map(|line| (TITLE_SIZE, line))
+            .collect();
+        lines.push((BODY_SIZE, String::new()));
+        for paragraph in body.lines() {
+            let wrapped = Self::wrap(paragraph, BODY_SIZE);
+            if wrapped.is_empty() {
+                lines.push((BODY_SIZE, String::new()));
+            }
+            lines.extend(wrapped.into_iter().map(|line| (BODY_SIZE, line)));
+        }
+
+        let mut pages = Vec::new();
+        let mut stream = String::new();
+        let mut y = PAGE_HEIGHT - MARGIN;
+        for (size, text) in lines {
+            let leading = size * LINE_HEIGHT;
+            if y - leading < MARGIN {
+                pages.push(std::mem::take(&mut stream));
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            y -= leading;
+            if !text.is_empty() {
+                let _ = writeln!(stream, "BT /F1 {} Tf {} {:.1} Td <{}> Tj ET", size, MARGIN, y, Self::hex(&text));
+            }
+        }
+        pages.push(stream);
+
+        Self::assemble(&pages)
+    }
+
+    /// ASCII 按半角、其他按全角估算宽度，优先在空格处断行
+    fn wrap(text: &str, size: f32) -> Vec<String> {
+        let max_width = PAGE_WIDTH - MARGIN * 2.0;
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        let mut width = 0.0;
+
+        for c in text.replace('\t', "    ").chars().filter(|c| !c.is_control()) {
+            let w = if c.is_ascii() { 0.5 } else { 1.0 } * size;
+            if width + w > max_width && !line.is_empty() {
+                let rest = match line.rfind(' ') {
+                    Some(at) if c.is_ascii_alphanumeric() && at > 0 => line.split_off(at + 1),
+                    _ => String::new(),
+                };
+                lines.push(line.trim_end().to_string());
+                width = rest.chars().map(|c| if c.is_ascii() { 0.5 } else { 1.0 } * size).sum();
+                line = rest;
+            }
+            line.push(c);
+            width += w;
+        }
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// UCS-2 大端十六进制字符串
+    fn hex(text: &str) -> String {
+        text.chars()
+            .map(|c| if (c as u32) <= 0xFFFF { format!("{:04X}", c as u32) } else { "003F".to_string() })
+            .collect()
+    }
+
+    fn assemble(pages: &[String]) -> Vec<u8> {
+        let page_ids: Vec<usize> = (0..pages.len()).map(|i| 6 + i * 2).collect();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+                pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light /Encoding /UniGB-UCS2-H /DescendantFonts [4 0 R] >>".to_string(),
+            "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 2 >> \
+             /FontDescriptor 5 0 R /DW 1000 /W [1 95 500] >>".to_string(),
+            "<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 /FontBBox [-25 -254 1000 880] \
+             /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >>".to_string(),
+        ];
+        for (page_id, stream) in page_ids.iter().zip(pages) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, page_id + 1
+            ));
+            objects.push(format!("<< /Length {} >>\nstream\n{}endstream", stream.len(), stream));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+
+        let xref = out.len();
+        let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+        out.into_bytes()
+    }
+}
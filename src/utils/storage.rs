@@ -0,0 +1,107 @@
+use std::path::{Component, Path, PathBuf};
+
+use actix_web::http::header::{Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue};
+use actix_web::web;
+use crate::error::AppError;
+
+/// 本地文件存储，key 是相对 root 的路径 (例如 `avatars/1-1700000000000.png`)
+/// 头像、附件等上传文件都通过它读写，换成对象存储时只需要改这里
+#[derive(Debug, Clone)]
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Storage { root: root.into() }
+    }
+
+    pub async fn save(&self, key: &str, data: Vec<u8>) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        web::block(move || {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, data)
+        })
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    pub async fn load(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        let path = self.path(key)?;
+        web::block(move || std::fs::read(&path))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AppError::NotFound("文件不存在".to_string()),
+                _ => AppError::Internal(e.to_string()),
+            })
+    }
+
+    /// 文件不存在视为删除成功
+    pub async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        web::block(move || match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        })
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// 只接受普通的相对路径，防止 `../` 跳出存储目录
+    fn path(&self, key: &str) -> Result<PathBuf, AppError> {
+        let relative = Path::new(key);
+        if key.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(AppError::BadRequest("非法的文件路径".to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// 根据扩展名推断 Content-Type
+    pub fn content_type(key: &str) -> &'static str {
+        match Path::new(key).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            Some("pdf") => "application/pdf",
+            Some("txt") | Some("md") => "text/plain; charset=utf-8",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// 图片 Content-Type 对应的扩展名，不是支持的图片格式返回 None
+    pub fn image_extension(content_type: &str) -> Option<&'static str> {
+        match content_type {
+            "image/png" => Some("png"),
+            "image/jpeg" => Some("jpg"),
+            "image/gif" => Some("gif"),
+            "image/webp" => Some("webp"),
+            _ => None,
+        }
+    }
+
+    /// 浏览器可以直接显示、又不会执行脚本的类型；其他类型 (html、svg 等) 一律按下载处理
+    pub fn is_inline_safe(content_type: &str) -> bool {
+        matches!(
+            content_type,
+            "image/png" | "image/jpeg" | "image/gif" | "image/webp" | "application/pdf" | "text/plain"
+        )
+    }
+
+    /// 文件名可能是中文，用 RFC 5987 的 `filename*`
+    pub fn content_disposition(inline: bool, filename: &str) -> ContentDisposition {
+        ContentDisposition {
+            disposition: if inline { DispositionType::Inline } else { DispositionType::Attachment },
+            parameters: vec![DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".to_string()),
+                language_tag: None,
+                value: filename.as_bytes().to_vec(),
+            })],
+        }
+    }
+}
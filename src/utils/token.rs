@@ -0,0 +1,22 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// 一次性令牌：明文只发给用户一次，数据库只存 sha256
+pub struct TokenUtil;
+
+impl TokenUtil {
+    /// 32 字节随机数，hex 编码
+    pub fn generate() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self::hex(&bytes)
+    }
+
+    pub fn hash(token: &str) -> String {
+        Self::hex(&Sha256::digest(token.as_bytes()))
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
\ No newline at end of file